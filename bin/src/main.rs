@@ -3,32 +3,98 @@ mod window;
 
 use window::Window;
 
+/// Frames between rewind states
+const REWIND_INTERVAL: u32 = 10;
+
 fn main() {
     env_logger::init();
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() != 2 {
+    let mut args: Vec<String> = std::env::args().collect();
+
+    // Backup type given by `--backup=<type>` overrides detection
+    let mut backup = None;
+    if let Some(arg) = take_option(&mut args, "--backup=") {
+        match arg.parse::<gba::BackupType>() {
+            Ok(kind) => backup = Some(kind),
+            Err(e) => {
+                println!("{}", e);
+                return usage();
+            }
+        }
+    }
+
+    // Memory for rewinding in megabytes, 0 disables it
+    let mut rewind = 64;
+    if let Some(arg) = take_option(&mut args, "--rewind=") {
+        match arg.parse::<usize>() {
+            Ok(mb) => rewind = mb,
+            Err(_) => return usage(),
+        }
+    }
+
+    // Input movie to be recorded to or played back from
+    let record = take_option(&mut args, "--record=");
+    let play = take_option(&mut args, "--play=");
+
+    if args.len() != 2 && args.len() != 3 {
         return usage();
     }
 
     let rom = std::fs::read(&args[1]).unwrap();
+
+    // Save file defaults to the ROM path with a .sav extension
+    let save = match args.get(2) {
+        Some(path) => std::path::PathBuf::from(path),
+        None => std::path::Path::new(&args[1]).with_extension("sav"),
+    };
     let bios = std::fs::read("rom/gba_bios.bin").unwrap();
     let mut gba = Box::new(gba::Gba::new());
 
     // Must be called before any operation
     gba.init();
     gba.bus.bios = bios;
-    gba.cart.rom = rom;
+    gba.load_rom(rom, backup);
+    gba.cart.attach_save(save).unwrap();
+    // Rewinding would break the input sequence of a movie
+    if rewind > 0 && record.is_none() && play.is_none() {
+        gba.enable_rewind(REWIND_INTERVAL, rewind << 20);
+    }
+
+    if let Some(path) = &play {
+        let movie = gba::Movie::from_bytes(&std::fs::read(path).unwrap()).unwrap();
+        gba.play_movie(movie).unwrap();
+    } else if record.is_some() {
+        let now = std::time::SystemTime::now();
+        let clock = now.duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        gba.record_movie(clock as i64);
+    }
 
     // let debugger = debug::init_debugger(&mut *gba);
     let mut window = Window::new("GameBar", 240, 160, 2);
     window.topmost(true);
 
     while window.is_open() {
-        gba.step_frame();
-        gba.keypad.set_input(window.get_input(), &mut gba.irqcnt);
+        // Hold R to run backwards
+        if window.is_key_down(minifb::Key::R) {
+            gba.rewind(1);
+        } else {
+            gba.step_frame();
+        }
+        // Movie input takes over the keypad during playback
+        if !gba.movie_playing() {
+            gba.keypad.set_input(window.get_input(), &mut gba.irqcnt);
+        }
         window.update_with_buffer(&gba.ppu.buffer);
         // debugger.display_sprite(6);
     }
+
+    if let Some(frame) = gba.movie_desync() {
+        println!("Movie playback went out of sync at frame {}", frame);
+    }
+    if let (Some(path), Some(movie)) = (&record, gba.stop_movie()) {
+        std::fs::write(path, movie.to_bytes()).unwrap();
+    }
+
+    gba.cart.flush_save().unwrap();
 }
 
 fn convert_buffer(orig: &[u16], new: &mut [u32]) {
@@ -38,6 +104,12 @@ fn convert_buffer(orig: &[u16], new: &mut [u32]) {
     }
 }
 
+/// Remove `--name=value` from arguments, returning the value
+fn take_option(args: &mut Vec<String>, prefix: &str) -> Option<String> {
+    let i = args.iter().position(|a| a.starts_with(prefix))?;
+    Some(args.remove(i)[prefix.len()..].to_string())
+}
+
 fn usage() {
-    println!("usage: GameBar <rom>");
+    println!("usage: GameBar [--backup=sram|eeprom|flash512|flash1m|sst|macronix64|panasonic|atmel|macronix128|sanyo] [--rewind=<megabytes>] [--record=<movie>|--play=<movie>] <rom> [save]");
 }
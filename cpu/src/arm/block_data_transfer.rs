@@ -62,12 +62,14 @@ pub fn execute(
 
     let &first = regs.next().unwrap();
     if l {
-        cpu.set_r(first, Cpu::ldr(addr & !0b11, bus));
+        let value = cpu.ldr(addr & !0b11, bus, Access::NonSeq);
+        cpu.set_r(first, value);
     } else {
-        Cpu::str(
+        cpu.str(
             addr & !0b11,
             cpu.r(first) + if first == 15 { 4 } else { 0 },
             bus,
+            Access::NonSeq,
         );
     };
     addr = addr.wrapping_add(4);
@@ -79,9 +81,15 @@ pub fn execute(
 
     for &r in regs {
         if l {
-            cpu.set_r(r, Cpu::ldr(addr & !0b11, bus));
+            let value = cpu.ldr(addr & !0b11, bus, Access::Seq);
+            cpu.set_r(r, value);
         } else {
-            Cpu::str(addr & !0b11, cpu.r(r) + if r == 15 { 4 } else { 0 }, bus);
+            cpu.str(
+                addr & !0b11,
+                cpu.r(r) + if r == 15 { 4 } else { 0 },
+                bus,
+                Access::Seq,
+            );
         };
 
         addr = addr.wrapping_add(4);
@@ -97,13 +105,10 @@ pub fn execute(
         }
     }
 
-    // One internal cycle plus n * memory waitstate
-    // cpu.cycles += 1 + count_cycles(rlist) * Bus::access_timing(address, 2);
-}
-
-#[allow(dead_code)]
-fn count_cycles(rlist: u32) -> i32 {
-    rlist.count_ones() as i32
+    // LDM takes one more internal cycle
+    if l {
+        cpu.internal(1);
+    }
 }
 
 fn rlist_to_index(mut rlist: u32) -> Vec<u32> {
@@ -36,6 +36,9 @@ pub fn execute(cpu: &mut Cpu, (i, opcode, s, rn, rd, operand2): (bool, u32, bool
     if !i && operand2.bit(4) {
         let rm = operand2.bits(3, 0);
 
+        // Reading the shift amount takes one internal cycle
+        cpu.internal(1);
+
         if rn == 15 {
             op1 += 4
         };
@@ -54,17 +54,28 @@ pub fn execute(
     }
 
     match lsh {
-        0b001 => Cpu::strh(address, value, bus),
-        0b010 => Cpu::strb(address, value, bus),
-        0b011 => Cpu::strh(address, value, bus),
-        0b101 => cpu.set_r(rd, Cpu::ldrh(address, bus)),
-        0b110 => cpu.set_r(rd, Cpu::ldrsb(address, bus)),
-        0b111 => cpu.set_r(rd, Cpu::ldrsh(address, bus)),
+        0b001 => cpu.strh(address, value, bus, Access::NonSeq),
+        0b010 => cpu.strb(address, value, bus, Access::NonSeq),
+        0b011 => cpu.strh(address, value, bus, Access::NonSeq),
+        0b101 => {
+            let value = cpu.ldrh(address, bus, Access::NonSeq);
+            cpu.set_r(rd, value)
+        }
+        0b110 => {
+            let value = cpu.ldrsb(address, bus, Access::NonSeq);
+            cpu.set_r(rd, value)
+        }
+        0b111 => {
+            let value = cpu.ldrsh(address, bus, Access::NonSeq);
+            cpu.set_r(rd, value)
+        }
         _ => unreachable!(),
     }
 
-    // One internal cycle plus memory waitstate
-    // cpu.cycles += 1 + Bus::access_timing(address, 1);
+    // Loads take one more internal cycle
+    if lsh.bit(2) {
+        cpu.internal(1);
+    }
 }
 
 #[cfg(test)]
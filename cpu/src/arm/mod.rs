@@ -5,7 +5,7 @@ mod branch_long;
 mod data_processing;
 mod disassemble;
 mod halfword_data_transfer;
-mod multiply_accumulate;
+pub mod multiply_accumulate;
 mod multiply_long_accumulate;
 mod psr_transfer;
 mod single_data_swap;
@@ -32,7 +32,12 @@ pub fn step(cpu: &mut Cpu, bus: &mut impl Bus) {
 
 #[inline]
 pub fn fetch(cpu: &mut Cpu, bus: &mut impl Bus) {
-    cpu.ir = Cpu::ldr(cpu.r(15) - 4, bus);
+    let pc = cpu.r(15);
+    cpu.ir = bus.load32((pc - 4) as usize);
+
+    // The instruction after next is prefetched while executing
+    cpu.cycles += bus.fetch_cycles((pc + 4) as usize, 2, cpu.fetch);
+    cpu.fetch = Access::Seq;
 }
 
 #[inline]
@@ -95,10 +100,8 @@ pub fn dispatch(cpu: &mut Cpu, bus: &mut impl Bus) {
 #[inline]
 fn multiply_swap(cpu: &mut Cpu, bus: &mut impl Bus, instr: u32) {
     match instr.bits(24, 20) {
-        0b00000 | 0b00001 | 0b00010 | 0b00011 => multiply_accumulate::interpret(cpu, instr),
-        0b01000 | 0b01001 | 0b01010 | 0b01011 | 0b01100 | 0b01101 | 0b01110 | 0b01111 => {
-            multiply_long_accumulate::interpret(cpu, instr)
-        }
+        0b00000..=0b00011 => multiply_accumulate::interpret(cpu, instr),
+        0b01000..=0b01111 => multiply_long_accumulate::interpret(cpu, instr),
 
         // Single data swap
         0b10000 | 0b10100 => single_data_swap::interpret(cpu, bus, instr),
@@ -40,7 +40,7 @@ pub fn execute(cpu: &mut Cpu, (a, s, rd, rn, rs, rm): (bool, bool, u32, u32, u32
         result = result.wrapping_add(cpu.r(rn));
 
         // One extra cycle for accumulation
-        cpu.cycles += 1;
+        cpu.internal(1);
     } else {
         // Rn should be set to 0 if not used as accumulate
         debug_assert_eq!(rn, 0);
@@ -57,19 +57,20 @@ pub fn execute(cpu: &mut Cpu, (a, s, rd, rn, rs, rm): (bool, bool, u32, u32, u32
     cpu.set_r(rd, result);
 
     // Internal cycles depending on size of operand
-    cpu.cycles += count_cycles(op0, op1);
+    cpu.internal(count_cycles(op1, true));
 }
 
-pub fn count_cycles(op0: u32, op1: u32) -> i32 {
-    if op0 < 0x100 && op1 < 0x100 {
-        1
-    } else if op0 < 0x10000 && op1 < 0x10000 {
-        2
-    } else if op0 < 0x1000000 && op1 < 0x1000000 {
-        3
-    } else {
-        4
+/// Internal cycles of a multiplication. The multiplier terminates early
+/// if the upper bits of `rs` are all zeroes, or all ones when signed.
+pub fn count_cycles(rs: u32, signed: bool) -> i32 {
+    for (m, shift) in [(1, 8), (2, 16), (3, 24)] {
+        let upper = rs >> shift;
+        if upper == 0 || (signed && upper == u32::MAX >> shift) {
+            return m;
+        }
     }
+
+    4
 }
 
 #[cfg(test)]
@@ -92,4 +93,12 @@ mod tests {
         assert!(cpu.cpsr.z);
         assert!(!cpu.cpsr.n);
     }
+
+    #[test]
+    fn early_termination() {
+        assert_eq!(count_cycles(0xff, false), 1);
+        assert_eq!(count_cycles(0xffffff00, true), 1);
+        assert_eq!(count_cycles(0xffffff00, false), 4);
+        assert_eq!(count_cycles(0x00ff0000, true), 3);
+    }
 }
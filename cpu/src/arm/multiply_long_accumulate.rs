@@ -39,6 +39,9 @@ pub fn execute(
     cpu: &mut Cpu,
     (u, a, s, rdhi, rdlo, rs, rm): (bool, bool, bool, u32, u32, u32, u32),
 ) {
+    // Multiplier is read before destination registers are written
+    let multiplier = cpu.r(rs);
+
     // 0 for u means unsigned
     let mut result = if !u {
         let operand1 = cpu.r(rm) as u64;
@@ -60,7 +63,7 @@ pub fn execute(
         result = result.wrapping_add(hi + lo);
 
         // Extra cycle for accumulation
-        cpu.cycles += 1;
+        cpu.internal(1);
     }
 
     if s {
@@ -74,7 +77,7 @@ pub fn execute(
     cpu.set_r(rdlo, result as u32);
 
     // Long multiplication consumes one additional internal cycle
-    cpu.cycles += 1 + count_cycles(cpu.r(rm), cpu.r(rs));
+    cpu.internal(1 + count_cycles(multiplier, u));
 }
 
 #[cfg(test)]
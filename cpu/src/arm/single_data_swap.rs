@@ -21,18 +21,16 @@ pub fn decode(instruction: u32) -> (bool, u32, u32, u32) {
 pub fn execute(cpu: &mut Cpu, bus: &mut impl Bus, (b, rn, rd, rm): (bool, u32, u32, u32)) {
     if b {
         let address = cpu.r(rn);
-        let temp = Cpu::ldrb(address, bus);
-        Cpu::strb(address, cpu.r(rm), bus);
-        cpu.set_r(rd, temp as u32);
-
-    // One internal cycle plus one load and one store
-    // cpu.cycles += 1 + 2 * Bus::access_timing(address, 0);
+        let temp = cpu.ldrb(address, bus, Access::NonSeq);
+        cpu.strb(address, cpu.r(rm), bus, Access::NonSeq);
+        cpu.set_r(rd, temp);
     } else {
         let address = cpu.r(rn);
-        let temp = Cpu::ldr(address, bus);
-        Cpu::str(address, cpu.r(rm), bus);
+        let temp = cpu.ldr(address, bus, Access::NonSeq);
+        cpu.str(address, cpu.r(rm), bus, Access::NonSeq);
         cpu.set_r(rd, temp);
-
-        // cpu.cycles += 1 + 2 * Bus::access_timing(address, 2);
     }
+
+    // One internal cycle plus one load and one store
+    cpu.internal(1);
 }
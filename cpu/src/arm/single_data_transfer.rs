@@ -60,14 +60,23 @@ pub fn execute(
 
     // Misaligned word access handled in `memory.rs`
     match lb {
-        0b00 => Cpu::str(address, value, bus),
-        0b01 => Cpu::strb(address, value, bus),
-        0b10 => cpu.set_r(rd, Cpu::ldr(address, bus)),
-        0b11 => cpu.set_r(rd, Cpu::ldrb(address, bus)),
+        0b00 => cpu.str(address, value, bus, Access::NonSeq),
+        0b01 => cpu.strb(address, value, bus, Access::NonSeq),
+        0b10 => {
+            let value = cpu.ldr(address, bus, Access::NonSeq);
+            cpu.set_r(rd, value)
+        }
+        0b11 => {
+            let value = cpu.ldrb(address, bus, Access::NonSeq);
+            cpu.set_r(rd, value)
+        }
         _ => unreachable!(),
     }
 
-    // cpu.cycles += 1 + Bus::access_timing(address, if lb.bit(0) { 0 } else { 2 });
+    // Loads take one more internal cycle
+    if lb.bit(1) {
+        cpu.internal(1);
+    }
 }
 
 #[cfg(test)]
@@ -94,7 +103,7 @@ mod tests {
             &mut bus,
             (false, true, false, false, 0b01, 0, 1, 1),
         );
-        assert_eq!(Cpu::ldrb(0x00, &bus), 0xff);
+        assert_eq!(cpu.ldrb(0x00, &mut bus, Access::NonSeq), 0xff);
     }
 
     #[test]
@@ -121,7 +130,7 @@ mod tests {
             &mut bus,
             (false, true, false, true, 0b00, 0, 1, 4),
         );
-        assert_eq!(Cpu::ldr(0x00, &bus), 0);
+        assert_eq!(cpu.ldr(0x00, &mut bus, Access::NonSeq), 0);
         assert_eq!(cpu.r(0), 0x00);
     }
 }
@@ -4,13 +4,24 @@
 //! access address.
 //! Current `Memory` implementation always clear lower bits of
 //! misaligned addresses.
+//! Each access adds its wait states to the cycles of the current instruction.
 
 use super::Cpu;
 use util::*;
 
 impl Cpu {
+    /// Add cycles of a data access. The opcode fetch following
+    /// a data access is always non sequential.
     #[inline]
-    pub fn ldr(address: u32, bus: &impl Bus) -> u32 {
+    fn data_access(&mut self, address: u32, size: u32, access: Access, bus: &mut impl Bus) {
+        self.cycles += bus.access_cycles(address as usize, size, access);
+        self.fetch = Access::NonSeq;
+    }
+
+    #[inline]
+    pub fn ldr(&mut self, address: u32, bus: &mut impl Bus, access: Access) -> u32 {
+        self.data_access(address, 2, access, bus);
+
         let rotation = (address & 0b11) * 8;
 
         // Memory loads are forcibly aligned
@@ -20,46 +31,60 @@ impl Cpu {
     }
 
     #[inline]
-    pub fn ldrb(address: u32, bus: &impl Bus) -> u32 {
+    pub fn ldrb(&mut self, address: u32, bus: &mut impl Bus, access: Access) -> u32 {
+        self.data_access(address, 0, access, bus);
+
         bus.load8(address as usize) as u32
     }
 
     #[inline]
-    pub fn ldrh(address: u32, bus: &impl Bus) -> u32 {
+    pub fn ldrh(&mut self, address: u32, bus: &mut impl Bus, access: Access) -> u32 {
+        self.data_access(address, 1, access, bus);
+
         let rotation = (address & 1) * 8;
 
         let value = bus.load16(address as usize) as u32;
 
-        value.rotate_right(rotation) as u32
+        value.rotate_right(rotation)
     }
 
     #[inline]
-    pub fn ldrsb(address: u32, bus: &impl Bus) -> u32 {
+    pub fn ldrsb(&mut self, address: u32, bus: &mut impl Bus, access: Access) -> u32 {
+        self.data_access(address, 0, access, bus);
+
         bus.load8(address as usize) as i8 as i32 as u32
     }
 
     #[inline]
-    pub fn ldrsh(address: u32, bus: &impl Bus) -> u32 {
+    pub fn ldrsh(&mut self, address: u32, bus: &mut impl Bus, access: Access) -> u32 {
         if address.bit(0) {
             // Misaligned LDRSH is effectively LDRSB
+            self.data_access(address, 0, access, bus);
             bus.load8(address as usize) as i8 as i32 as u32
         } else {
+            self.data_access(address, 1, access, bus);
             bus.load16(address as usize) as i16 as i32 as u32
         }
     }
 
     #[inline]
-    pub fn str(address: u32, value: u32, bus: &mut impl Bus) {
+    pub fn str(&mut self, address: u32, value: u32, bus: &mut impl Bus, access: Access) {
+        self.data_access(address, 2, access, bus);
+
         bus.store32(address as usize, value)
     }
 
     #[inline]
-    pub fn strb(address: u32, value: u32, bus: &mut impl Bus) {
+    pub fn strb(&mut self, address: u32, value: u32, bus: &mut impl Bus, access: Access) {
+        self.data_access(address, 0, access, bus);
+
         bus.store8(address as usize, value as u8)
     }
 
     #[inline]
-    pub fn strh(address: u32, value: u32, bus: &mut impl Bus) {
+    pub fn strh(&mut self, address: u32, value: u32, bus: &mut impl Bus, access: Access) {
+        self.data_access(address, 1, access, bus);
+
         bus.store16(address as usize, value as u16)
     }
 }
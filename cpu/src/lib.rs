@@ -8,7 +8,7 @@ mod shifter;
 mod thumb;
 
 use register::{Cpsr, PsrMode};
-use util::Bus;
+use util::{snapshot, Access, Bus};
 
 #[derive(Clone)]
 pub struct Cpu {
@@ -25,11 +25,28 @@ pub struct Cpu {
     // 18 - 20: R13_abt, R14_abt, SPSR_abt
     // 21 - 23: R13_irq, R14_irq, SPSR_irq
     // 24 - 26: R13_und, R14_und, SPSR_und
+    fetch: Access,              // Cycle type of the next opcode fetch
     pub cycles: i32,            // Ticks consumed for current instruction
+    internal: i32,              // Internal cycles of current instruction
+    refill: bool,               // Pipeline is flushed and yet to be refilled
     pub remaining: i32,         // Remaining ticks till run finish,
     pub callback: Option<fn()>, // Callback before an instruction is executed
 }
 
+// Callback is set by the frontend, not part of the state
+snapshot!(Cpu {
+    ir,
+    r,
+    cpsr,
+    spsr,
+    bank,
+    fetch,
+    cycles,
+    internal,
+    refill,
+    remaining
+});
+
 impl Cpu {
     pub fn new() -> Self {
         Self {
@@ -42,7 +59,10 @@ impl Cpu {
             spsr: 0,
             bank: [0; 27],
 
+            fetch: Access::NonSeq,
             cycles: 0,
+            internal: 0,
+            refill: false,
             remaining: 0,
             callback: None,
         }
@@ -51,9 +71,10 @@ impl Cpu {
     pub fn skip_bios(&mut self) {
         self.r[15] = 0x08000004;
         self.r[13] = 0x03007f00;
+        self.cpsr = Cpsr::from(0x1f); // System mode with interrupts enabled
 
         self.bank[5] = 0x03007f00; // User SP
-        self.bank[12] = 0x03007fa0; // IRQ SP
+        self.bank[21] = 0x03007fa0; // IRQ SP
         self.bank[15] = 0x03007fe0; // Supervisor SP
     }
 
@@ -71,8 +92,12 @@ impl Cpu {
             f();
         }
 
-        // At least one sequential cycle for any instruction
-        self.cycles = 1;
+        // Opcode fetch and data accesses add their own cycles
+        self.cycles = 0;
+        self.internal = 0;
+
+        // Flushed by an interrupt
+        self.refill(bus);
 
         if self.in_thumb_mode() {
             thumb::step(self, bus);
@@ -80,6 +105,13 @@ impl Cpu {
             arm::step(self, bus);
         }
 
+        self.refill(bus);
+
+        // The bus may make use of internal cycles, e.g. game pak prefetch
+        if self.internal > 0 {
+            bus.idle(self.internal);
+        }
+
         self.cycles
     }
 
@@ -96,14 +128,37 @@ impl Cpu {
         }
     }
 
+    /// Add internal cycles, where no memory access takes place
+    #[inline]
+    pub fn internal(&mut self, cycles: i32) {
+        self.cycles += cycles;
+        self.internal += cycles;
+    }
+
     pub fn flush(&mut self) {
         // Instruction address are forcibly word / halfword aligned
         self.r[15] &= !(self.inst_width() - 1);
 
         self.r[15] += self.inst_width();
+        self.refill = true;
+    }
+
+    /// A write to R15 or branch will add 1N + 1S cycles for
+    /// fetching the first two instructions at the new address
+    #[inline]
+    fn refill(&mut self, bus: &mut impl Bus) {
+        if !self.refill {
+            return;
+        }
+
+        let width = self.inst_width();
+        let size = width / 2;
+        let pc = self.r[15];
 
-        // A write to R15 or branch will add 1S + 1N cycles
-        // self.cycles += Bus::access_timing(self.r[15], self.inst_width() / 2);
+        self.cycles += bus.fetch_cycles((pc - width) as usize, size, Access::NonSeq);
+        self.cycles += bus.fetch_cycles(pc as usize, size, Access::Seq);
+        self.fetch = Access::Seq;
+        self.refill = false;
     }
 
     pub fn interrupt(&mut self, mode: PsrMode, lr: u32, pc: u32) {
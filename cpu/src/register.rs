@@ -114,6 +114,28 @@ impl From<u32> for PsrMode {
     }
 }
 
+/// Saved as the raw register value
+impl Snapshot for Cpsr {
+    fn save_state(&self, out: &mut Vec<u8>) {
+        u32::from(*self).save_state(out);
+    }
+
+    fn load_state(&mut self, input: &mut &[u8]) -> std::io::Result<()> {
+        let mut word = 0u32;
+        word.load_state(input)?;
+        if ![
+            0b10000, 0b10001, 0b10010, 0b10011, 0b10111, 0b11011, 0b11111,
+        ]
+        .contains(&word.bits(4, 0))
+        {
+            return Err(snapshot::invalid("CPSR mode"));
+        }
+
+        *self = Cpsr::from(word);
+        Ok(())
+    }
+}
+
 impl From<PsrMode> for u32 {
     fn from(mode: PsrMode) -> Self {
         mode as u32
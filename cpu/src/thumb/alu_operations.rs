@@ -1,4 +1,5 @@
 use crate::alu;
+use crate::arm::multiply_accumulate::count_cycles;
 use crate::Cpu;
 use util::*;
 
@@ -44,6 +45,13 @@ fn execute(cpu: &mut Cpu, (op, rs, rd): (u32, u32, u32)) {
     };
     cpu.set_flags(flags);
 
+    match op {
+        // Shift by register takes one internal cycle
+        0b0010 | 0b0011 | 0b0100 | 0b0111 => cpu.internal(1),
+        0b1101 => cpu.internal(count_cycles(op1, true)),
+        _ => (),
+    }
+
     if op != 0b1000 && op != 0b1010 && op != 0b1011 {
         cpu.set_r(rd, result);
     }
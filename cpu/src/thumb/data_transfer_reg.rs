@@ -25,16 +25,34 @@ fn execute(cpu: &mut Cpu, bus: &mut impl Bus, (lbh, ro, rb, rd): (u32, u32, u32,
 
     // Misaligned halfword access is not handled
     match lbh {
-        0b000 => Cpu::str(address, cpu.r(rd), bus),
-        0b001 => Cpu::strh(address, cpu.r(rd), bus),
-        0b010 => Cpu::strb(address, cpu.r(rd), bus),
-        0b011 => cpu.set_r(rd, Cpu::ldrsb(address, bus)),
-        0b100 => cpu.set_r(rd, Cpu::ldr(address, bus)),
-        0b101 => cpu.set_r(rd, Cpu::ldrh(address, bus)),
-        0b110 => cpu.set_r(rd, Cpu::ldrb(address, bus)),
-        0b111 => cpu.set_r(rd, Cpu::ldrsh(address, bus)),
+        0b000 => cpu.str(address, cpu.r(rd), bus, Access::NonSeq),
+        0b001 => cpu.strh(address, cpu.r(rd), bus, Access::NonSeq),
+        0b010 => cpu.strb(address, cpu.r(rd), bus, Access::NonSeq),
+        0b011 => {
+            let value = cpu.ldrsb(address, bus, Access::NonSeq);
+            cpu.set_r(rd, value)
+        }
+        0b100 => {
+            let value = cpu.ldr(address, bus, Access::NonSeq);
+            cpu.set_r(rd, value)
+        }
+        0b101 => {
+            let value = cpu.ldrh(address, bus, Access::NonSeq);
+            cpu.set_r(rd, value)
+        }
+        0b110 => {
+            let value = cpu.ldrb(address, bus, Access::NonSeq);
+            cpu.set_r(rd, value)
+        }
+        0b111 => {
+            let value = cpu.ldrsh(address, bus, Access::NonSeq);
+            cpu.set_r(rd, value)
+        }
         _ => unreachable!(),
     };
 
-    // cpu.cycles += 1 + Bus::access_timing(address, size);
+    // Loads take one more internal cycle
+    if lbh > 0b010 {
+        cpu.internal(1);
+    }
 }
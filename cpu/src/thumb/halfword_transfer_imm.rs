@@ -24,10 +24,14 @@ fn execute(cpu: &mut Cpu, bus: &mut impl Bus, (l, offset5, rb, rd): (bool, u32,
     let address = cpu.r(rb) + (offset5 << 1);
 
     if l {
-        cpu.set_r(rd, Cpu::ldrh(address, bus));
+        let value = cpu.ldrh(address, bus, Access::NonSeq);
+        cpu.set_r(rd, value);
     } else {
-        Cpu::strh(address, cpu.r(rd), bus);
+        cpu.strh(address, cpu.r(rd), bus, Access::NonSeq);
     }
 
-    // cpu.cycles += 1 + Bus::access_timing(address, 1);
+    // Loads take one more internal cycle
+    if l {
+        cpu.internal(1);
+    }
 }
@@ -38,7 +38,12 @@ pub fn step(cpu: &mut Cpu, bus: &mut impl Bus) {
 
 #[inline]
 pub fn fetch(cpu: &mut Cpu, bus: &mut impl Bus) {
-    cpu.ir = Cpu::ldrh(cpu.r(15) - 2, bus);
+    let pc = cpu.r(15);
+    cpu.ir = bus.load16((pc - 2) as usize) as u32;
+
+    // The instruction after next is prefetched while executing
+    cpu.cycles += bus.fetch_cycles((pc + 2) as usize, 1, cpu.fetch);
+    cpu.fetch = Access::Seq;
 }
 
 #[inline]
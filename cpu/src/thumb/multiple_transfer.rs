@@ -24,9 +24,11 @@ fn execute(cpu: &mut Cpu, bus: &mut impl Bus, (l, rb, rlist): (bool, u32, u32))
     if rlist == 0 {
         let addr = cpu.r(rb);
         if l {
-            cpu.set_r(15, Cpu::ldr(addr & !0b11, bus));
+            let value = cpu.ldr(addr & !0b11, bus, Access::NonSeq);
+            cpu.set_r(15, value);
+            cpu.internal(1);
         } else {
-            Cpu::str(addr & !0b11, cpu.r(15) + 2, bus);
+            cpu.str(addr & !0b11, cpu.r(15) + 2, bus, Access::NonSeq);
         }
         cpu.set_r(rb, addr.wrapping_add(0x40));
     } else {
@@ -20,7 +20,8 @@ fn execute(cpu: &mut Cpu, bus: &mut impl Bus, (rd, word8): (u32, u32)) {
     // Bit 1 of PC is forced to 0 to ensure it is word aligned.
     let address = (cpu.r(15) & 0xfffffffc) + (word8 << 2);
 
-    cpu.set_r(rd, Cpu::ldr(address, bus));
+    let value = cpu.ldr(address, bus, Access::NonSeq);
+    cpu.set_r(rd, value);
 
-    // cpu.cycles += 1 + Bus::access_timing(address, 2);
+    cpu.internal(1);
 }
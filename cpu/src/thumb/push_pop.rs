@@ -26,7 +26,7 @@ fn execute(cpu: &mut Cpu, bus: &mut impl Bus, (l, r, rlist): (bool, bool, u32))
     // onto the stack
     if r && !l {
         cpu.set_r(13, cpu.r(13) - 4);
-        Cpu::str(cpu.r(13), cpu.r(14), bus);
+        cpu.str(cpu.r(13), cpu.r(14), bus, Access::NonSeq);
     }
 
     if rlist != 0 {
@@ -36,7 +36,13 @@ fn execute(cpu: &mut Cpu, bus: &mut impl Bus, (l, r, rlist): (bool, bool, u32))
     // Pop values off the stack into registers specified by rlist,
     // and then Pop PC off the stack
     if r && l {
-        cpu.set_r(15, Cpu::ldr(cpu.r(13), bus));
+        let value = cpu.ldr(cpu.r(13), bus, Access::NonSeq);
+        cpu.set_r(15, value);
         cpu.set_r(13, cpu.r(13) + 4);
+
+        // Otherwise counted by the block transfer
+        if rlist == 0 {
+            cpu.internal(1);
+        }
     }
 }
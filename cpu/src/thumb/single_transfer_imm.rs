@@ -25,13 +25,21 @@ fn execute(cpu: &mut Cpu, bus: &mut impl Bus, (bl, offset5, rb, rd): (u32, u32,
     let address = base + (offset5 << if bl.bit(1) { 0 } else { 2 });
 
     match bl {
-        0b00 => Cpu::str(address, cpu.r(rd), bus),
-        0b01 => cpu.set_r(rd, Cpu::ldr(address, bus)),
-        0b10 => Cpu::strb(address, cpu.r(rd), bus),
-        0b11 => cpu.set_r(rd, Cpu::ldrb(address, bus)),
+        0b00 => cpu.str(address, cpu.r(rd), bus, Access::NonSeq),
+        0b01 => {
+            let value = cpu.ldr(address, bus, Access::NonSeq);
+            cpu.set_r(rd, value)
+        }
+        0b10 => cpu.strb(address, cpu.r(rd), bus, Access::NonSeq),
+        0b11 => {
+            let value = cpu.ldrb(address, bus, Access::NonSeq);
+            cpu.set_r(rd, value)
+        }
         _ => unreachable!(),
     }
 
-    // 1I + 1N
-    // cpu.cycles += 1 + Bus::access_timing(base, if bl.bit(1) { 0 } else { 2 });
+    // Loads take one more internal cycle
+    if bl.bit(0) {
+        cpu.internal(1);
+    }
 }
@@ -21,10 +21,14 @@ fn execute(cpu: &mut Cpu, bus: &mut impl Bus, (l, rd, word8): (bool, u32, u32))
     let address = cpu.r(13) + (word8 << 2);
 
     if l {
-        cpu.set_r(rd, Cpu::ldr(address, bus));
+        let value = cpu.ldr(address, bus, Access::NonSeq);
+        cpu.set_r(rd, value);
     } else {
-        Cpu::str(address, cpu.r(rd), bus);
+        cpu.str(address, cpu.r(rd), bus, Access::NonSeq);
     }
 
-    // cpu.cycles += 1 + Bus::access_timing(address, 2);
+    // Loads take one more internal cycle
+    if l {
+        cpu.internal(1);
+    }
 }
@@ -0,0 +1,130 @@
+use util::*;
+
+/// Volume envelope shared by the square and noise channels
+#[derive(Clone, Copy)]
+pub struct Envelope {
+    pub initial: u16,     // Volume on restart, 0 - 15
+    pub increase_f: bool, // Envelope direction, 1 - increase, 0 - decrease
+    pub step: u16,        // Step time in units of 1/64 s, 0 - no envelope
+    pub volume: u16,      // Current volume
+    pub counter: u16,     // Ticks left until next step
+}
+
+util::snapshot!(Envelope {
+    initial,
+    increase_f,
+    step,
+    volume,
+    counter
+});
+
+impl Envelope {
+    pub fn new() -> Self {
+        Self {
+            initial: 0,
+            increase_f: false,
+            step: 0,
+            volume: 0,
+            counter: 0,
+        }
+    }
+
+    /// Decode the upper byte of a duty / envelope register
+    pub fn set(&mut self, value: u16) {
+        self.step = value.bits(10, 8) as u16;
+        self.increase_f = value.bit(11);
+        self.initial = value.bits(15, 12) as u16;
+    }
+
+    pub fn restart(&mut self) {
+        self.volume = self.initial;
+        self.counter = self.step;
+    }
+
+    /// A channel whose envelope starts silent and decreases is switched off
+    pub fn dac_enabled(&self) -> bool {
+        self.initial != 0 || self.increase_f
+    }
+
+    /// Clocked at 64 Hz by the frame sequencer
+    pub fn clock(&mut self) {
+        if self.step == 0 {
+            return;
+        }
+
+        // Step time may be set without a restart, leaving no count loaded
+        self.counter = self.counter.saturating_sub(1);
+        if self.counter == 0 {
+            self.counter = self.step;
+
+            if self.increase_f && self.volume < 15 {
+                self.volume += 1;
+            } else if !self.increase_f && self.volume > 0 {
+                self.volume -= 1;
+            }
+        }
+    }
+}
+
+/// Sound length counter, the channel stops when it runs out
+#[derive(Clone, Copy)]
+pub struct Length {
+    pub max: u16,       // 64 for square and noise channels, 256 for wave
+    pub counter: u16,   // Remaining length
+    pub enable_f: bool, // Stop output when counter reaches zero
+}
+
+util::snapshot!(Length {
+    max,
+    counter,
+    enable_f
+});
+
+impl Length {
+    pub fn new(max: u16) -> Self {
+        Self {
+            max,
+            counter: 0,
+            enable_f: false,
+        }
+    }
+
+    pub fn set(&mut self, value: u16) {
+        self.counter = self.max - value;
+    }
+
+    pub fn restart(&mut self) {
+        if self.counter == 0 {
+            self.counter = self.max;
+        }
+    }
+
+    /// Clocked at 256 Hz by the frame sequencer, return true if expired
+    pub fn clock(&mut self) -> bool {
+        if self.enable_f && self.counter > 0 {
+            self.counter -= 1;
+            return self.counter == 0;
+        }
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn step_set_after_restart() {
+        let mut envelope = Envelope::new();
+        envelope.set(0xf000);
+        envelope.restart();
+
+        // Decrease every tick, without triggering again
+        envelope.set(0xf100);
+        envelope.clock();
+        assert_eq!(envelope.volume, 14);
+        envelope.clock();
+        assert_eq!(envelope.volume, 13);
+    }
+}
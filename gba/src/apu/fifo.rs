@@ -0,0 +1,91 @@
+/// Direct Sound FIFO A / B, 32 bytes of signed 8 bit samples
+#[derive(Clone)]
+pub struct Fifo {
+    pub data: [i8; 32],
+    pub head: usize, // Index of the oldest sample
+    pub len: usize,  // Number of samples queued
+
+    pub sample: i8,   // Sample currently played
+    pub timer: usize, // Timer whose overflow pops a sample, 0 or 1
+}
+
+util::snapshot!(Fifo {
+    data,
+    head,
+    len,
+    sample,
+    timer
+});
+
+impl Fifo {
+    pub fn new() -> Self {
+        Self {
+            data: [0; 32],
+            head: 0,
+            len: 0,
+
+            sample: 0,
+            timer: 0,
+        }
+    }
+
+    /// Queue two samples, lower byte first. Writes to a full FIFO are dropped.
+    pub fn push(&mut self, value: u16) {
+        for b in value.to_le_bytes().iter() {
+            if self.len < 32 {
+                self.data[(self.head + self.len) % 32] = *b as i8;
+                self.len += 1;
+            }
+        }
+    }
+
+    pub fn pop(&mut self) {
+        if self.len > 0 {
+            self.sample = self.data[self.head];
+            self.head = (self.head + 1) % 32;
+            self.len -= 1;
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.head = 0;
+        self.len = 0;
+        self.sample = 0;
+    }
+
+    /// DMA refills 4 words once half of the FIFO is consumed
+    #[inline]
+    pub fn needs_refill(&self) -> bool {
+        self.len <= 16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push_pop() {
+        let mut fifo = Fifo::new();
+
+        fifo.push(0x80ff);
+        fifo.pop();
+        assert_eq!(fifo.sample, -1);
+        fifo.pop();
+        assert_eq!(fifo.sample, -128);
+
+        // Latched sample is kept on underflow
+        fifo.pop();
+        assert_eq!(fifo.sample, -128);
+    }
+
+    #[test]
+    fn overflow() {
+        let mut fifo = Fifo::new();
+
+        for _ in 0..20 {
+            fifo.push(0x0101);
+        }
+        assert_eq!(fifo.len, 32);
+    }
+}
@@ -0,0 +1,216 @@
+//! Audio processing unit
+//! Four legacy PSG channels inherited from the Game Boy and
+//! two Direct Sound FIFOs fed by DMA, mixed according to
+//! SOUNDCNT_L / SOUNDCNT_H and SOUNDBIAS.
+
+mod envelope;
+mod fifo;
+mod noise;
+mod square;
+mod wave;
+
+pub use fifo::Fifo;
+pub use noise::Noise;
+pub use square::Square;
+pub use wave::Wave;
+
+use crate::dma::Dma;
+use util::*;
+
+/// Cycles between frame sequencer steps, 16.78 MHz / 512 Hz
+pub static SEQUENCER_PERIOD: i32 = 32768;
+
+pub struct Apu {
+    pub square: [Square; 2], // Channel 1 - 2
+    pub wave: Wave,          // Channel 3
+    pub noise: Noise,        // Channel 4
+    pub fifo: [Fifo; 2],     // Direct Sound A - B
+
+    pub soundcnt_l: u16, // PSG master volume / enable
+    pub soundcnt_h: u16, // Mixing ratio
+    pub enable: bool,    // Master enable, bit 7 of SOUNDCNT_X
+    pub soundbias: u16,  // Bias level / amplitude resolution
+
+    pub sequencer: i32, // Cycles accumulated towards next frame sequencer step
+    pub step: u32,      // Frame sequencer step, 0 - 7
+
+    /// Interleaved stereo samples at `sample_rate`
+    pub samples: Vec<i16>,
+}
+
+// Samples are output of the current frame
+util::snapshot!(Apu {
+    square,
+    wave,
+    noise,
+    fifo,
+    soundcnt_l,
+    soundcnt_h,
+    enable,
+    soundbias,
+    sequencer,
+    step
+});
+
+impl Apu {
+    pub fn new() -> Self {
+        Self {
+            square: [Square::new(), Square::new()],
+            wave: Wave::new(),
+            noise: Noise::new(),
+            fifo: [Fifo::new(), Fifo::new()],
+
+            soundcnt_l: 0,
+            soundcnt_h: 0,
+            enable: false,
+            soundbias: 0x200,
+
+            sequencer: 0,
+            step: 0,
+
+            samples: Vec::new(),
+        }
+    }
+
+    /// Output sample rate in Hz, 32768 << amplitude resolution
+    #[inline]
+    pub fn sample_rate(&self) -> u32 {
+        32768 << self.soundbias.bits(15, 14)
+    }
+
+    #[inline]
+    pub fn sample_period(&self) -> i32 {
+        512 >> self.soundbias.bits(15, 14)
+    }
+
+    /// Output a sample, return cycles until the next one is due
+    pub fn sample(&mut self) -> i32 {
+        let period = self.sample_period();
+
+        if self.enable {
+            self.step_channels(period);
+        }
+
+        self.mix();
+
+        period
+    }
+
+    /// Overflow of timer 0 or 1 feeds the next sample to FIFOs that selected it
+    pub fn timer_overflow(&mut self, timer: usize, dma: &mut Dma) {
+        for (i, fifo) in self.fifo.iter_mut().enumerate() {
+            if fifo.timer != timer {
+                continue;
+            }
+
+            fifo.pop();
+            if fifo.needs_refill() {
+                dma.request_fifo(i);
+            }
+        }
+    }
+
+    fn step_channels(&mut self, cycles: i32) {
+        self.sequencer += cycles;
+        while self.sequencer >= SEQUENCER_PERIOD {
+            self.sequencer -= SEQUENCER_PERIOD;
+            self.clock_sequencer();
+        }
+
+        self.square[0].step(cycles);
+        self.square[1].step(cycles);
+        self.wave.step(cycles);
+        self.noise.step(cycles);
+    }
+
+    /// Length counters are clocked at 256 Hz, sweep at 128 Hz, envelope at 64 Hz
+    fn clock_sequencer(&mut self) {
+        if self.step & 1 == 0 {
+            self.square[0].clock_length();
+            self.square[1].clock_length();
+            self.wave.clock_length();
+            self.noise.clock_length();
+        }
+
+        if self.step % 4 == 2 {
+            self.square[0].clock_sweep();
+        }
+
+        if self.step == 7 {
+            self.square[0].envelope.clock();
+            self.square[1].envelope.clock();
+            self.noise.envelope.clock();
+        }
+
+        self.step = (self.step + 1) % 8;
+    }
+
+    fn mix(&mut self) {
+        let (mut left, mut right) = (0, 0);
+
+        if self.enable {
+            let psg = [
+                self.square[0].output(),
+                self.square[1].output(),
+                self.wave.output(),
+                self.noise.output(),
+            ];
+
+            for (i, &s) in psg.iter().enumerate() {
+                if self.soundcnt_l.bit(8 + i as u32) {
+                    right += s as i32;
+                }
+                if self.soundcnt_l.bit(12 + i as u32) {
+                    left += s as i32;
+                }
+            }
+
+            // Master volume 1 - 8, then PSG ratio 25%, 50%, 100%
+            let shift = [2, 1, 0, 0][self.soundcnt_h.bits(1, 0) as usize];
+            right = (right * (self.soundcnt_l.bits(2, 0) as i32 + 1)) >> shift;
+            left = (left * (self.soundcnt_l.bits(6, 4) as i32 + 1)) >> shift;
+
+            // Direct Sound volume 50% or 100%, then per side enable
+            for (i, fifo) in self.fifo.iter().enumerate() {
+                let b = 4 * i as u32;
+                let volume = if self.soundcnt_h.bit(2 + i as u32) {
+                    4
+                } else {
+                    2
+                };
+                let s = fifo.sample as i32 * volume;
+
+                if self.soundcnt_h.bit(8 + b) {
+                    right += s;
+                }
+                if self.soundcnt_h.bit(9 + b) {
+                    left += s;
+                }
+            }
+        }
+
+        self.samples.push(self.bias(left));
+        self.samples.push(self.bias(right));
+    }
+
+    /// Add bias and clip to the 10 bit output range, then scale to 16 bits
+    fn bias(&self, sample: i32) -> i16 {
+        let level = self.soundbias.bits(9, 0) as i32;
+        let output = (sample + level).clamp(0, 0x3ff);
+
+        ((output - 0x200) << 6) as i16
+    }
+
+    /// Disabling the master enable resets all PSG registers
+    pub fn reset_psg(&mut self) {
+        let ram = self.wave.ram;
+
+        self.square = [Square::new(), Square::new()];
+        self.wave = Wave::new();
+        self.wave.ram = ram;
+        self.noise = Noise::new();
+        self.soundcnt_l = 0;
+        self.step = 0;
+        self.sequencer = 0;
+    }
+}
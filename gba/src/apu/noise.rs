@@ -0,0 +1,127 @@
+use super::envelope::{Envelope, Length};
+
+/// Dividing ratio of the noise frequency, in cycles
+pub static DIVISOR: [i32; 8] = [32, 64, 128, 192, 256, 320, 384, 448];
+
+/// Channel 4, pseudo random noise from a linear feedback shift register
+#[derive(Clone)]
+pub struct Noise {
+    pub envelope_r: u16, // Raw length / envelope register
+    pub control: u16,    // Raw frequency / control register
+
+    pub ratio: usize,   // Dividing ratio of frequency
+    pub narrow_f: bool, // Counter width, 1 - 7 bits, 0 - 15 bits
+    pub shift: u16,     // Shift clock frequency
+    pub envelope: Envelope,
+    pub length: Length,
+
+    pub lfsr: u16,
+    pub high: bool, // Current output level
+    pub timer: i32,
+    pub enable: bool,
+}
+
+util::snapshot!(Noise {
+    envelope_r,
+    control,
+    ratio,
+    narrow_f,
+    shift,
+    envelope,
+    length,
+    lfsr,
+    high,
+    timer,
+    enable
+});
+
+impl Noise {
+    pub fn new() -> Self {
+        Self {
+            envelope_r: 0,
+            control: 0,
+
+            ratio: 0,
+            narrow_f: false,
+            shift: 0,
+            envelope: Envelope::new(),
+            length: Length::new(64),
+
+            lfsr: 0,
+            high: false,
+            timer: 0,
+            enable: false,
+        }
+    }
+
+    #[inline]
+    pub fn period(&self) -> i32 {
+        DIVISOR[self.ratio] << self.shift
+    }
+
+    pub fn restart(&mut self) {
+        self.enable = self.envelope.dac_enabled();
+        self.timer = self.period();
+        self.lfsr = if self.narrow_f { 0x40 } else { 0x4000 };
+        self.envelope.restart();
+        self.length.restart();
+    }
+
+    pub fn step(&mut self, cycles: i32) {
+        // Shift clock frequency 14 and 15 are prohibited
+        if self.shift > 13 {
+            return;
+        }
+
+        self.timer -= cycles;
+        while self.timer <= 0 {
+            self.timer += self.period();
+
+            let carry = self.lfsr & 1 == 1;
+            self.lfsr >>= 1;
+            if carry {
+                self.lfsr ^= if self.narrow_f { 0x60 } else { 0x6000 };
+            }
+            self.high = carry;
+        }
+    }
+
+    pub fn clock_length(&mut self) {
+        if self.length.clock() {
+            self.enable = false;
+        }
+    }
+
+    /// Signed channel output, -15 - 15
+    pub fn output(&self) -> i16 {
+        if !self.enable {
+            return 0;
+        }
+
+        let volume = self.envelope.volume as i16;
+        if self.high {
+            volume
+        } else {
+            -volume
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lfsr_period() {
+        let mut noise = Noise::new();
+
+        noise.envelope.initial = 15;
+        noise.narrow_f = true;
+        noise.restart();
+
+        // 7 bit counter repeats every 127 shifts
+        let start = noise.lfsr;
+        noise.step(noise.period() * 127);
+        assert_eq!(noise.lfsr, start);
+    }
+}
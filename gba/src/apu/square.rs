@@ -0,0 +1,201 @@
+use super::envelope::{Envelope, Length};
+
+/// Waveform of each duty cycle, one bit for each of the 8 steps
+pub static DUTY: [u8; 4] = [0b0000_0001, 0b1000_0001, 0b1000_0111, 0b0111_1110];
+
+/// Channel 1 and 2, tone with optional frequency sweep
+#[derive(Clone)]
+pub struct Square {
+    pub sweep: u16,   // Raw sweep register, channel 1 only
+    pub duty: u16,    // Raw duty / length / envelope register
+    pub control: u16, // Raw frequency / control register
+
+    pub duty_n: usize,  // Duty cycle index, 12.5%, 25%, 50%, 75%
+    pub frequency: u16, // 11 bit rate, period is (2048 - n) * 16 cycles
+    pub envelope: Envelope,
+    pub length: Length,
+
+    pub sweep_time: u16,    // Sweep step time in units of 1/128 s
+    pub sweep_dec_f: bool,  // Sweep direction, 1 - decrease, 0 - increase
+    pub sweep_shift: u16,   // Number of sweep shift
+    pub sweep_counter: u16, // Ticks left until next sweep
+    pub sweep_f: bool,      // Sweep unit is running
+    pub shadow: u16,        // Frequency the sweep unit operates on
+
+    pub timer: i32, // Cycles left until next duty step
+    pub phase: u32, // Current duty step, 0 - 7
+    pub enable: bool,
+}
+
+util::snapshot!(Square {
+    sweep,
+    duty,
+    control,
+    duty_n,
+    frequency,
+    envelope,
+    length,
+    sweep_time,
+    sweep_dec_f,
+    sweep_shift,
+    sweep_counter,
+    sweep_f,
+    shadow,
+    timer,
+    phase,
+    enable
+});
+
+impl Square {
+    pub fn new() -> Self {
+        Self {
+            sweep: 0,
+            duty: 0,
+            control: 0,
+
+            duty_n: 0,
+            frequency: 0,
+            envelope: Envelope::new(),
+            length: Length::new(64),
+
+            sweep_time: 0,
+            sweep_dec_f: false,
+            sweep_shift: 0,
+            sweep_counter: 0,
+            sweep_f: false,
+            shadow: 0,
+
+            timer: 0,
+            phase: 0,
+            enable: false,
+        }
+    }
+
+    #[inline]
+    pub fn period(&self) -> i32 {
+        (2048 - self.frequency as i32) * 16
+    }
+
+    pub fn restart(&mut self) {
+        self.enable = self.envelope.dac_enabled();
+        self.timer = self.period();
+        self.envelope.restart();
+        self.length.restart();
+
+        self.shadow = self.frequency;
+        self.sweep_counter = self.sweep_reload();
+        self.sweep_f = self.sweep_time != 0 || self.sweep_shift != 0;
+        if self.sweep_shift != 0 && self.sweep_calculate() > 2047 {
+            self.enable = false;
+        }
+    }
+
+    pub fn step(&mut self, cycles: i32) {
+        self.timer -= cycles;
+        while self.timer <= 0 {
+            self.timer += self.period();
+            self.phase = (self.phase + 1) % 8;
+        }
+    }
+
+    pub fn clock_length(&mut self) {
+        if self.length.clock() {
+            self.enable = false;
+        }
+    }
+
+    /// Clocked at 128 Hz by the frame sequencer
+    pub fn clock_sweep(&mut self) {
+        if self.sweep_counter > 0 {
+            self.sweep_counter -= 1;
+        }
+
+        if self.sweep_counter > 0 {
+            return;
+        }
+
+        self.sweep_counter = self.sweep_reload();
+
+        if self.sweep_f && self.sweep_time != 0 {
+            let frequency = self.sweep_calculate();
+
+            if frequency > 2047 {
+                self.enable = false;
+            } else if self.sweep_shift != 0 {
+                self.shadow = frequency;
+                self.frequency = frequency;
+
+                // Overflow is checked again with the new frequency
+                if self.sweep_calculate() > 2047 {
+                    self.enable = false;
+                }
+            }
+        }
+    }
+
+    fn sweep_reload(&self) -> u16 {
+        if self.sweep_time == 0 {
+            8
+        } else {
+            self.sweep_time
+        }
+    }
+
+    fn sweep_calculate(&self) -> u16 {
+        let delta = self.shadow >> self.sweep_shift;
+
+        if self.sweep_dec_f {
+            self.shadow - delta
+        } else {
+            self.shadow + delta
+        }
+    }
+
+    /// Signed channel output, -15 - 15
+    pub fn output(&self) -> i16 {
+        if !self.enable {
+            return 0;
+        }
+
+        let volume = self.envelope.volume as i16;
+        if DUTY[self.duty_n] >> self.phase & 1 == 1 {
+            volume
+        } else {
+            -volume
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sweep_overflow() {
+        let mut square = Square::new();
+
+        square.envelope.initial = 15;
+        square.frequency = 2000;
+        square.sweep_time = 1;
+        square.sweep_shift = 1;
+        square.restart();
+
+        // 2000 + 2000 >> 1 overflows the 11 bit frequency
+        assert!(!square.enable);
+    }
+
+    #[test]
+    fn duty_step() {
+        let mut square = Square::new();
+
+        square.envelope.initial = 15;
+        square.duty_n = 2;
+        square.frequency = 2047;
+        square.restart();
+        assert_eq!(square.output(), 15);
+
+        // 50% duty is low for steps 3 - 6
+        square.step(16 * 3);
+        assert_eq!(square.output(), -15);
+    }
+}
@@ -0,0 +1,122 @@
+use super::envelope::Length;
+
+/// Channel 3, plays back 4 bit samples stored in wave ram
+#[derive(Clone)]
+pub struct Wave {
+    pub select: u16,  // Raw stop / wave ram select register
+    pub volume: u16,  // Raw length / volume register
+    pub control: u16, // Raw frequency / control register
+
+    pub dimension_f: bool, // 1 - Two banks of 32 samples, 0 - one bank
+    pub bank: usize,       // Bank selected for playback
+    pub playback_f: bool,  // Channel DAC on / off
+    pub level: u16,        // 0 - mute, 1 - 100%, 2 - 50%, 3 - 25%
+    pub force_f: bool,     // Force 75% volume
+    pub frequency: u16,    // 11 bit rate, period is (2048 - n) * 8 cycles
+    pub length: Length,
+
+    pub ram: [u8; 32], // Two banks of 16 bytes wave ram
+    pub position: usize,
+    pub timer: i32,
+    pub enable: bool,
+}
+
+util::snapshot!(Wave {
+    select,
+    volume,
+    control,
+    dimension_f,
+    bank,
+    playback_f,
+    level,
+    force_f,
+    frequency,
+    length,
+    ram,
+    position,
+    timer,
+    enable
+});
+
+impl Wave {
+    pub fn new() -> Self {
+        Self {
+            select: 0,
+            volume: 0,
+            control: 0,
+
+            dimension_f: false,
+            bank: 0,
+            playback_f: false,
+            level: 0,
+            force_f: false,
+            frequency: 0,
+            length: Length::new(256),
+
+            ram: [0; 32],
+            position: 0,
+            timer: 0,
+            enable: false,
+        }
+    }
+
+    #[inline]
+    pub fn period(&self) -> i32 {
+        (2048 - self.frequency as i32) * 8
+    }
+
+    pub fn restart(&mut self) {
+        self.enable = self.playback_f;
+        self.timer = self.period();
+        self.position = 0;
+        self.length.restart();
+    }
+
+    pub fn step(&mut self, cycles: i32) {
+        let samples = if self.dimension_f { 64 } else { 32 };
+
+        self.timer -= cycles;
+        while self.timer <= 0 {
+            self.timer += self.period();
+            self.position = (self.position + 1) % samples;
+        }
+    }
+
+    pub fn clock_length(&mut self) {
+        if self.length.clock() {
+            self.enable = false;
+        }
+    }
+
+    /// Wave ram is accessed through the bank not selected for playback
+    #[inline]
+    pub fn ram_offset(&self, offset: usize) -> usize {
+        (self.bank ^ 1) * 16 + offset % 16
+    }
+
+    /// Signed channel output, -15 - 15
+    pub fn output(&self) -> i16 {
+        if !self.enable {
+            return 0;
+        }
+
+        // In two bank mode the bank not selected is played after the selected one
+        let bank = (self.bank + self.position / 32) % 2;
+        let index = self.position % 32;
+        let byte = self.ram[bank * 16 + index / 2];
+
+        // Upper nibble is played first
+        let nibble = if index & 1 == 0 {
+            byte >> 4
+        } else {
+            byte & 0xf
+        };
+        let sample = nibble as i16 * 2 - 15;
+
+        match (self.force_f, self.level) {
+            (true, _) => sample * 3 / 4,
+            (false, 0) => 0,
+            (false, n) => sample >> (n - 1),
+        }
+    }
+}
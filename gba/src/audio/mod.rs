@@ -0,0 +1,90 @@
+//! Audio output, samples produced by the APU each frame are
+//! handed to an `AudioSink` provided by the frontend.
+
+mod wav;
+
+pub use wav::WavWriter;
+
+/// Destination of the sound output of every emulated frame
+pub trait AudioSink {
+    /// Receive interleaved stereo samples played at `rate` Hz
+    fn write(&mut self, samples: &[i16], rate: u32);
+
+    /// End of output, return the first error met while writing
+    fn finish(self: Box<Self>) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Linear interpolating sample rate converter for interleaved stereo samples.
+/// Integer only, so the output is bit exact across hosts.
+pub struct Resampler {
+    pub rate: u32,      // Output sample rate
+    position: u64,      // Position between previous and next input frame, in 1 / rate
+    previous: [i16; 2], // Last input frame
+}
+
+impl Resampler {
+    pub fn new(rate: u32) -> Self {
+        Self {
+            rate,
+            position: 0,
+            previous: [0; 2],
+        }
+    }
+
+    /// Convert `input` sampled at `input_rate` Hz, appending to `output`
+    pub fn process(&mut self, input: &[i16], input_rate: u32, output: &mut Vec<i16>) {
+        let rate = self.rate as u64;
+
+        for frame in input.chunks_exact(2) {
+            while self.position < rate {
+                for (p, &n) in self.previous.iter().zip(frame) {
+                    let delta = (n as i64 - *p as i64) * self.position as i64 / rate as i64;
+                    output.push((*p as i64 + delta) as i16);
+                }
+
+                self.position += input_rate as u64;
+            }
+
+            self.position -= rate;
+            self.previous = [frame[0], frame[1]];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resample_same_rate() {
+        let mut resampler = Resampler::new(32768);
+        let mut output = Vec::new();
+
+        resampler.process(&[1, 2, 3, 4, 5, 6], 32768, &mut output);
+
+        // Output lags behind input by one frame
+        assert_eq!(output, vec![0, 0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn resample_down() {
+        let mut resampler = Resampler::new(32768);
+        let mut output = Vec::new();
+
+        resampler.process(&[100; 2 * 256], 65536, &mut output);
+        assert_eq!(output.len(), 256);
+    }
+
+    #[test]
+    fn resample_up() {
+        let mut resampler = Resampler::new(65536);
+        let mut output = Vec::new();
+
+        resampler.process(&[0, 0, 100, -100], 32768, &mut output);
+
+        // Midpoint is interpolated
+        assert_eq!(output, vec![0, 0, 0, 0, 0, 0, 50, -50]);
+    }
+}
@@ -0,0 +1,140 @@
+use std::fs::File;
+use std::io::{BufWriter, Error, Result, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use super::{AudioSink, Resampler};
+
+/// Record sound output into a 16 bit stereo PCM wave file.
+/// Samples are resampled to a fixed output rate, as the APU rate
+/// changes with the amplitude resolution of SOUNDBIAS.
+pub struct WavWriter<W: Write + Seek> {
+    inner: W,
+    resampler: Resampler,
+    buffer: Vec<i16>,
+    length: u32,          // Bytes of sample data written
+    error: Option<Error>, // First write failure, nothing is written after
+}
+
+impl WavWriter<BufWriter<File>> {
+    pub fn create(path: impl AsRef<Path>, rate: u32) -> Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?), rate)
+    }
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    pub fn new(inner: W, rate: u32) -> Result<Self> {
+        let mut w = Self {
+            inner,
+            resampler: Resampler::new(rate),
+            buffer: Vec::new(),
+            length: 0,
+            error: None,
+        };
+
+        w.write_header()?;
+        Ok(w)
+    }
+
+    fn write_header(&mut self) -> Result<()> {
+        let rate = self.resampler.rate;
+        let w = &mut self.inner;
+
+        w.write_all(b"RIFF")?;
+        w.write_all(&(36 + self.length).to_le_bytes())?;
+        w.write_all(b"WAVE")?;
+
+        w.write_all(b"fmt ")?;
+        w.write_all(&16u32.to_le_bytes())?;
+        w.write_all(&1u16.to_le_bytes())?; // PCM
+        w.write_all(&2u16.to_le_bytes())?; // Stereo
+        w.write_all(&rate.to_le_bytes())?;
+        w.write_all(&(rate * 4).to_le_bytes())?; // Byte rate
+        w.write_all(&4u16.to_le_bytes())?; // Block align
+        w.write_all(&16u16.to_le_bytes())?; // Bits per sample
+
+        w.write_all(b"data")?;
+        w.write_all(&self.length.to_le_bytes())?;
+
+        Ok(())
+    }
+
+    /// Patch chunk sizes in the header so the file is valid up to this point
+    pub fn flush(&mut self) -> Result<()> {
+        self.inner.seek(SeekFrom::Start(0))?;
+        self.write_header()?;
+        self.inner.seek(SeekFrom::End(0))?;
+        self.inner.flush()
+    }
+
+    /// Finalize the file, returning the first error met while recording
+    pub fn finish(mut self) -> Result<()> {
+        match self.error.take() {
+            Some(e) => Err(e),
+            None => self.flush(),
+        }
+    }
+}
+
+impl<W: Write + Seek> Drop for WavWriter<W> {
+    fn drop(&mut self) {
+        self.flush().ok();
+    }
+}
+
+impl<W: Write + Seek> AudioSink for WavWriter<W> {
+    fn write(&mut self, samples: &[i16], rate: u32) {
+        if self.error.is_some() {
+            return;
+        }
+
+        self.buffer.clear();
+        self.resampler.process(samples, rate, &mut self.buffer);
+
+        for s in self.buffer.iter() {
+            if let Err(e) = self.inner.write_all(&s.to_le_bytes()) {
+                util::warn!("Failed to write wave file, recording stopped: {}", e);
+                self.error = Some(e);
+                return;
+            }
+            self.length += 2;
+        }
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        WavWriter::finish(*self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn header() {
+        let mut data = Vec::new();
+        let mut wav = WavWriter::new(Cursor::new(&mut data), 32768).unwrap();
+
+        wav.write(&[0; 2 * 100], 32768);
+        drop(wav);
+
+        assert_eq!(data.len(), 44 + 400);
+        assert_eq!(&data[0..4], b"RIFF");
+        assert_eq!(util::into32(&data[4..8]), 36 + 400);
+        assert_eq!(util::into32(&data[24..28]), 32768);
+        assert_eq!(util::into32(&data[40..44]), 400);
+    }
+
+    #[test]
+    fn write_error() {
+        let mut data = [0; 44 + 100];
+        let mut wav = WavWriter::new(Cursor::new(&mut data[..]), 32768).unwrap();
+
+        // Runs out of space halfway, later writes are dropped
+        wav.write(&[0; 2 * 50], 32768);
+        wav.write(&[0; 2 * 50], 32768);
+        assert_eq!(wav.length, 100);
+        assert!(wav.finish().is_err());
+        assert_eq!(util::into32(&data[40..44]), 100);
+    }
+}
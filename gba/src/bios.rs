@@ -0,0 +1,92 @@
+//! Stand-in for the BIOS when none is provided. Only interrupt dispatch is
+//! implemented, through the user handler at 0x03007ffc as the real BIOS
+//! does. Software interrupts return right away without doing anything, so
+//! games relying on BIOS calls such as division, decompression or
+//! VBlankIntrWait will not run correctly.
+
+use crate::Gba;
+
+/// ARM code placed at the exception vectors
+static CODE: [u32; 8] = [
+    0xe1b0f00e, // 0x00 Reset: movs pc, lr
+    0xe1b0f00e, // 0x04 Undefined: movs pc, lr
+    0xe1b0f00e, // 0x08 SWI: movs pc, lr
+    0xe25ef004, // 0x0c Prefetch abort: subs pc, lr, #4
+    0xe25ef008, // 0x10 Data abort: subs pc, lr, #8
+    0xe1a00000, // 0x14 Reserved: nop
+    0xe92d500f, // 0x18 IRQ: stmfd sp!, {r0-r3, r12, lr}
+    0xe3a00301, // mov r0, #0x04000000
+];
+
+static IRQ: [u32; 4] = [
+    0xe28fe000, // add lr, pc, #0
+    0xe510f004, // ldr pc, [r0, #-4]
+    0xe8bd500f, // ldmfd sp!, {r0-r3, r12, lr}
+    0xe25ef004, // subs pc, lr, #4
+];
+
+/// 16KB BIOS image with the stub code
+pub fn stub() -> Vec<u8> {
+    let mut bios: Vec<u8> = CODE
+        .iter()
+        .chain(IRQ.iter())
+        .flat_map(|c| c.to_le_bytes())
+        .collect();
+    bios.resize(0x4000, 0);
+    bios
+}
+
+impl Gba {
+    /// Start at the cartridge entry point, with the stub BIOS installed
+    pub fn skip_bios(&mut self) {
+        self.bus.bios = stub();
+        self.cpu.skip_bios();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use util::Bus;
+
+    #[test]
+    fn vblank_irq() {
+        let mut gba = Box::new(Gba::new());
+        gba.init();
+
+        // Install the handler, enable VBlank IRQ, then call SWI 0 forever.
+        // The handler counts interrupts at 0x03000000.
+        let code = [
+            0xe3a00301u32, // mov r0, #0x04000000
+            0xe3a01403,    // mov r1, #0x03000000
+            0xe2811c7f,    // add r1, r1, #0x7f00
+            0xe28f2020,    // add r2, pc, #0x20
+            0xe58120fc,    // str r2, [r1, #0xfc]
+            0xe3a02008,    // mov r2, #8
+            0xe1c020b4,    // strh r2, [r0, #4]
+            0xe3a02001,    // mov r2, #1
+            0xe2803c02,    // add r3, r0, #0x200
+            0xe1c320b0,    // strh r2, [r3]
+            0xe5832008,    // str r2, [r3, #8]
+            0xef000000,    // loop: swi 0
+            0xeafffffd,    // b loop
+            0xe3a03403,    // handler: mov r3, #0x03000000
+            0xe5932000,    // ldr r2, [r3]
+            0xe2822001,    // add r2, r2, #1
+            0xe5832000,    // str r2, [r3]
+            0xe2803c02,    // add r3, r0, #0x200
+            0xe3a02001,    // mov r2, #1
+            0xe1c320b2,    // strh r2, [r3, #2]
+            0xe12fff1e,    // bx lr
+        ];
+        let mut rom: Vec<u8> = code.iter().flat_map(|c| c.to_le_bytes()).collect();
+        rom.resize(0x200, 0);
+        gba.load_rom(rom, None);
+        gba.skip_bios();
+
+        for _ in 0..10 {
+            gba.step_frame();
+        }
+        assert_eq!(gba.bus.load32(0x03000000), 10);
+    }
+}
@@ -0,0 +1,200 @@
+use crate::apu::{Apu, Noise, Square, Wave};
+use util::*;
+
+// Length and frequency bits are write only, reads return the rest
+
+impl Square {
+    #[inline]
+    pub fn get_sweep(&self) -> u16 {
+        self.sweep & 0x007f
+    }
+
+    #[inline]
+    pub fn set_sweep(&mut self, value: u16) {
+        self.sweep = value;
+        self.sweep_shift = value.bits(2, 0) as u16;
+        self.sweep_dec_f = value.bit(3);
+        self.sweep_time = value.bits(6, 4) as u16;
+    }
+
+    #[inline]
+    pub fn get_duty(&self) -> u16 {
+        self.duty & 0xffc0
+    }
+
+    #[inline]
+    pub fn set_duty(&mut self, value: u16) {
+        self.duty = value;
+        self.length.set(value.bits(5, 0) as u16);
+        self.duty_n = value.bits(7, 6) as usize;
+        self.envelope.set(value);
+    }
+
+    #[inline]
+    pub fn get_control(&self) -> u16 {
+        self.control & 0x4000
+    }
+
+    #[inline]
+    pub fn set_control(&mut self, value: u16) {
+        self.control = value;
+        self.frequency = value.bits(10, 0) as u16;
+        self.length.enable_f = value.bit(14);
+
+        if value.bit(15) {
+            self.restart();
+        }
+    }
+}
+
+impl Wave {
+    #[inline]
+    pub fn get_select(&self) -> u16 {
+        self.select & 0x00e0
+    }
+
+    #[inline]
+    pub fn set_select(&mut self, value: u16) {
+        self.select = value;
+        self.dimension_f = value.bit(5);
+        self.bank = value.bits(6, 6) as usize;
+        self.playback_f = value.bit(7);
+
+        if !self.playback_f {
+            self.enable = false;
+        }
+    }
+
+    #[inline]
+    pub fn get_volume(&self) -> u16 {
+        self.volume & 0xe000
+    }
+
+    #[inline]
+    pub fn set_volume(&mut self, value: u16) {
+        self.volume = value;
+        self.length.set(value.bits(7, 0) as u16);
+        self.level = value.bits(14, 13) as u16;
+        self.force_f = value.bit(15);
+    }
+
+    #[inline]
+    pub fn get_control(&self) -> u16 {
+        self.control & 0x4000
+    }
+
+    #[inline]
+    pub fn set_control(&mut self, value: u16) {
+        self.control = value;
+        self.frequency = value.bits(10, 0) as u16;
+        self.length.enable_f = value.bit(14);
+
+        if value.bit(15) {
+            self.restart();
+        }
+    }
+
+    #[inline]
+    pub fn get_ram(&self, offset: usize) -> u16 {
+        let i = self.ram_offset(offset);
+        u16::from_le_bytes([self.ram[i], self.ram[i + 1]])
+    }
+
+    #[inline]
+    pub fn set_ram(&mut self, offset: usize, value: u16) {
+        let i = self.ram_offset(offset);
+        self.ram[i..i + 2].copy_from_slice(&value.to_le_bytes());
+    }
+}
+
+impl Noise {
+    #[inline]
+    pub fn get_envelope(&self) -> u16 {
+        self.envelope_r & 0xff00
+    }
+
+    #[inline]
+    pub fn set_envelope(&mut self, value: u16) {
+        self.envelope_r = value;
+        self.length.set(value.bits(5, 0) as u16);
+        self.envelope.set(value);
+    }
+
+    #[inline]
+    pub fn get_control(&self) -> u16 {
+        self.control & 0x40ff
+    }
+
+    #[inline]
+    pub fn set_control(&mut self, value: u16) {
+        self.control = value;
+        self.ratio = value.bits(2, 0) as usize;
+        self.narrow_f = value.bit(3);
+        self.shift = value.bits(7, 4) as u16;
+        self.length.enable_f = value.bit(14);
+
+        if value.bit(15) {
+            self.restart();
+        }
+    }
+}
+
+impl Apu {
+    #[inline]
+    pub fn get_soundcnt_l(&self) -> u16 {
+        self.soundcnt_l & 0xff77
+    }
+
+    #[inline]
+    pub fn set_soundcnt_l(&mut self, value: u16) {
+        self.soundcnt_l = value;
+    }
+
+    #[inline]
+    pub fn get_soundcnt_h(&self) -> u16 {
+        self.soundcnt_h & 0x770f
+    }
+
+    #[inline]
+    pub fn set_soundcnt_h(&mut self, value: u16) {
+        self.soundcnt_h = value;
+        self.fifo[0].timer = value.bits(10, 10) as usize;
+        self.fifo[1].timer = value.bits(14, 14) as usize;
+
+        if value.bit(11) {
+            self.fifo[0].reset();
+        }
+        if value.bit(15) {
+            self.fifo[1].reset();
+        }
+    }
+
+    /// Bit 0 - 3 are the read only channel status flags
+    #[inline]
+    pub fn get_soundcnt_x(&self) -> u16 {
+        (self.enable as u16) << 7
+            | (self.noise.enable as u16) << 3
+            | (self.wave.enable as u16) << 2
+            | (self.square[1].enable as u16) << 1
+            | (self.square[0].enable as u16)
+    }
+
+    #[inline]
+    pub fn set_soundcnt_x(&mut self, value: u16) {
+        self.enable = value.bit(7);
+
+        if !self.enable {
+            self.reset_psg();
+        }
+    }
+
+    #[inline]
+    pub fn get_soundbias(&self) -> u16 {
+        self.soundbias
+    }
+
+    #[inline]
+    pub fn set_soundbias(&mut self, value: u16) {
+        self.soundbias = value & 0xc3fe;
+    }
+}
@@ -1,4 +1,6 @@
+use crate::bus::GbaBus;
 use crate::dma::DmaChannel;
+use crate::event::Event;
 use util::*;
 
 impl DmaChannel {
@@ -64,9 +66,6 @@ impl DmaChannel {
     #[inline]
     pub fn set_control(&mut self, value: u16) {
         self.control = value;
-
-        // Initiate a DMA if start mode is immediate
-        self.active = self.enable() && self.start() == 0;
     }
 
     #[inline]
@@ -104,3 +103,15 @@ impl DmaChannel {
         self.control.bit(15)
     }
 }
+
+impl GbaBus {
+    pub fn set_dma_control(&mut self, index: usize, value: u16) {
+        let channel = &mut self.dma.channel[index];
+        channel.set_control(value);
+
+        // Initiate a DMA if start mode is immediate, after 2 cycles of delay
+        if channel.enable() && channel.start() == 0 {
+            self.scheduler.schedule(Event::Dma(index), 2);
+        }
+    }
+}
@@ -1,4 +1,5 @@
-use crate::interrupt::IrqController;
+use crate::interrupt::{IrqController, Power};
+use util::*;
 
 impl IrqController {
     #[inline]
@@ -31,4 +32,24 @@ impl IrqController {
         util::info!("{:b}", value);
         self.irf &= !value;
     }
+
+    #[inline]
+    pub fn get_postflg(&self) -> u16 {
+        self.postflg as u16
+    }
+
+    #[inline]
+    pub fn set_postflg(&mut self, value: u8) {
+        self.postflg = value & 1;
+    }
+
+    /// HALTCNT is write only, bit 7 selects stop instead of halt
+    #[inline]
+    pub fn set_haltcnt(&mut self, value: u8) {
+        self.power = if value.bit(7) {
+            Power::Stop
+        } else {
+            Power::Halt
+        };
+    }
 }
@@ -1,3 +1,4 @@
+mod apu;
 mod dma;
 mod interrupt;
 mod keypad;
@@ -8,8 +9,8 @@ use super::{Bus, GbaBus};
 impl GbaBus {
     #[inline]
     pub fn ioram_load8(&self, offset: usize) -> u8 {
-        let value = self.ioram_load16(offset);
-        value.to_le_bytes()[offset as usize & 1]
+        let value = self.ioram_load16(offset & !1);
+        value.to_le_bytes()[offset & 1]
     }
 
     pub fn ioram_load16(&self, offset: usize) -> u16 {
@@ -30,6 +31,25 @@ impl GbaBus {
             0x048 => self.ppu.window.get_winin(),
             0x04a => self.ppu.window.get_winout(),
             // Window boundary register are write only
+            0x050 => self.ppu.blend.get_bldcnt(),
+            0x052 => self.ppu.blend.get_bldalpha(),
+            // BLDY is write only
+            0x060 => self.apu.square[0].get_sweep(),
+            0x062 => self.apu.square[0].get_duty(),
+            0x064 => self.apu.square[0].get_control(),
+            0x068 => self.apu.square[1].get_duty(),
+            0x06c => self.apu.square[1].get_control(),
+            0x070 => self.apu.wave.get_select(),
+            0x072 => self.apu.wave.get_volume(),
+            0x074 => self.apu.wave.get_control(),
+            0x078 => self.apu.noise.get_envelope(),
+            0x07c => self.apu.noise.get_control(),
+            0x080 => self.apu.get_soundcnt_l(),
+            0x082 => self.apu.get_soundcnt_h(),
+            0x084 => self.apu.get_soundcnt_x(),
+            0x088 => self.apu.get_soundbias(),
+            0x090..=0x09e => self.apu.wave.get_ram(offset - 0x090),
+
             0x0b0 => self.dma.channel[0].get_src_l(),
             0x0b2 => self.dma.channel[0].get_src_h(),
             0x0b4 => self.dma.channel[0].get_dst_l(),
@@ -58,13 +78,13 @@ impl GbaBus {
             0x0dc => self.dma.channel[3].get_count(),
             0x0de => self.dma.channel[3].get_control(),
 
-            0x100 => self.timers.timer[0].get_counter(),
+            0x100 => self.timers.timer[0].get_counter(self.scheduler.now),
             0x102 => self.timers.timer[0].get_control(),
-            0x104 => self.timers.timer[1].get_counter(),
+            0x104 => self.timers.timer[1].get_counter(self.scheduler.now),
             0x106 => self.timers.timer[1].get_control(),
-            0x108 => self.timers.timer[2].get_counter(),
+            0x108 => self.timers.timer[2].get_counter(self.scheduler.now),
             0x10a => self.timers.timer[2].get_control(),
-            0x10c => self.timers.timer[3].get_counter(),
+            0x10c => self.timers.timer[3].get_counter(self.scheduler.now),
             0x10e => self.timers.timer[3].get_control(),
 
             0x130 => self.keypad.get_input(),
@@ -72,7 +92,9 @@ impl GbaBus {
 
             0x200 => self.irqcnt.get_ie(),
             0x202 => self.irqcnt.get_irf(),
+            0x204 => self.get_waitcnt(),
             0x208 => self.irqcnt.get_ime(),
+            0x300 => self.irqcnt.get_postflg(),
             _ => Self::unhandled(true, 2, (4 << 24) + offset),
         }
     }
@@ -86,12 +108,19 @@ impl GbaBus {
 
     #[inline]
     pub fn ioram_store8(&mut self, offset: usize, value: u8) {
-        let mut old = self.ioram_load16(offset).to_le_bytes();
-        old[offset as usize & 1] = value;
+        // POSTFLG and HALTCNT share a halfword but are written separately
+        match offset {
+            0x300 => return self.irqcnt.set_postflg(value),
+            0x301 => return self.irqcnt.set_haltcnt(value),
+            _ => (),
+        }
+
+        let mut old = self.ioram_load16(offset & !1).to_le_bytes();
+        old[offset & 1] = value;
         let new = u16::from_le_bytes(old);
 
         // Beware of side effects
-        self.ioram_store16(offset, new);
+        self.ioram_store16(offset & !1, new);
     }
 
     pub fn ioram_store16(&mut self, offset: usize, value: u16) {
@@ -139,6 +168,30 @@ impl GbaBus {
             0x046 => self.ppu.window.set_win1v(value),
             0x048 => self.ppu.window.set_winin(value),
             0x04a => self.ppu.window.set_winout(value),
+            0x04c => self.ppu.mosaic.set_mosaic(value),
+            0x050 => self.ppu.blend.set_bldcnt(value),
+            0x052 => self.ppu.blend.set_bldalpha(value),
+            0x054 => self.ppu.blend.set_bldy(value),
+
+            // Sound registers are locked while master enable is cleared
+            0x060..=0x081 if !self.apu.enable => {}
+            0x060 => self.apu.square[0].set_sweep(value),
+            0x062 => self.apu.square[0].set_duty(value),
+            0x064 => self.apu.square[0].set_control(value),
+            0x068 => self.apu.square[1].set_duty(value),
+            0x06c => self.apu.square[1].set_control(value),
+            0x070 => self.apu.wave.set_select(value),
+            0x072 => self.apu.wave.set_volume(value),
+            0x074 => self.apu.wave.set_control(value),
+            0x078 => self.apu.noise.set_envelope(value),
+            0x07c => self.apu.noise.set_control(value),
+            0x080 => self.apu.set_soundcnt_l(value),
+            0x082 => self.apu.set_soundcnt_h(value),
+            0x084 => self.apu.set_soundcnt_x(value),
+            0x088 => self.apu.set_soundbias(value),
+            0x090..=0x09e => self.apu.wave.set_ram(offset - 0x090, value),
+            0x0a0 | 0x0a2 => self.apu.fifo[0].push(value),
+            0x0a4 | 0x0a6 => self.apu.fifo[1].push(value),
 
             // DMA 0 - 3
             0x0b0 => self.dma.channel[0].set_src_l(value),
@@ -146,38 +199,38 @@ impl GbaBus {
             0x0b4 => self.dma.channel[0].set_dst_l(value),
             0x0b6 => self.dma.channel[0].set_dst_h(value),
             0x0b8 => self.dma.channel[0].set_count(value),
-            0x0ba => self.dma.channel[0].set_control(value),
+            0x0ba => self.set_dma_control(0, value),
 
             0x0bc => self.dma.channel[1].set_src_l(value),
             0x0be => self.dma.channel[1].set_src_h(value),
             0x0c0 => self.dma.channel[1].set_dst_l(value),
             0x0c2 => self.dma.channel[1].set_dst_h(value),
             0x0c4 => self.dma.channel[1].set_count(value),
-            0x0c6 => self.dma.channel[1].set_control(value),
+            0x0c6 => self.set_dma_control(1, value),
 
             0x0c8 => self.dma.channel[2].set_src_l(value),
             0x0ca => self.dma.channel[2].set_src_h(value),
             0x0cc => self.dma.channel[2].set_dst_l(value),
             0x0ce => self.dma.channel[2].set_dst_h(value),
             0x0d0 => self.dma.channel[2].set_count(value),
-            0x0d2 => self.dma.channel[2].set_control(value),
+            0x0d2 => self.set_dma_control(2, value),
 
             0x0d4 => self.dma.channel[3].set_src_l(value),
             0x0d6 => self.dma.channel[3].set_src_h(value),
             0x0d8 => self.dma.channel[3].set_dst_l(value),
             0x0da => self.dma.channel[3].set_dst_h(value),
             0x0dc => self.dma.channel[3].set_count(value),
-            0x0de => self.dma.channel[3].set_control(value),
+            0x0de => self.set_dma_control(3, value),
 
             // Timer 0 - 3
             0x100 => self.timers.timer[0].set_reload(value),
-            0x102 => self.timers.timer[0].set_control(value),
+            0x102 => self.set_timer_control(0, value),
             0x104 => self.timers.timer[1].set_reload(value),
-            0x106 => self.timers.timer[1].set_control(value),
+            0x106 => self.set_timer_control(1, value),
             0x108 => self.timers.timer[2].set_reload(value),
-            0x10a => self.timers.timer[2].set_control(value),
+            0x10a => self.set_timer_control(2, value),
             0x10c => self.timers.timer[3].set_reload(value),
-            0x10e => self.timers.timer[3].set_control(value),
+            0x10e => self.set_timer_control(3, value),
 
             // Keypad input is read only
             0x132 => self.keypad.set_control(value),
@@ -185,7 +238,12 @@ impl GbaBus {
             // Interrupt Controller
             0x200 => self.irqcnt.set_ie(value),
             0x202 => self.irqcnt.ack_irf(value),
+            0x204 => self.set_waitcnt(value),
             0x208 => self.irqcnt.set_ime(value),
+            0x300 => {
+                self.irqcnt.set_postflg(value as u8);
+                self.irqcnt.set_haltcnt((value >> 8) as u8);
+            }
             _ => Self::unhandled(false, 2, (4 << 24) + offset),
         }
     }
@@ -1,11 +1,14 @@
+use crate::bus::GbaBus;
+use crate::event::Scheduler;
 use crate::timer::Timer;
 use crate::timer::PRESCALER;
+use crate::Gba;
 use util::*;
 
 impl Timer {
     #[inline]
-    pub fn get_counter(&self) -> u16 {
-        self.counter
+    pub fn get_counter(&self, now: u64) -> u16 {
+        self.counter_at(now)
     }
 
     #[inline]
@@ -19,7 +22,9 @@ impl Timer {
     }
 
     #[inline]
-    pub fn set_control(&mut self, value: u16) {
+    pub fn set_control(&mut self, value: u16, scheduler: &mut Scheduler) {
+        self.sync(scheduler.now);
+
         // Reload on switching on timer
         if !self.enable && value.bit(7) {
             self.counter = self.reload
@@ -30,5 +35,17 @@ impl Timer {
         self.cascade_f = value.bit(2);
         self.irq_f = value.bit(6);
         self.enable = value.bit(7);
+
+        self.schedule(scheduler);
+    }
+}
+
+impl GbaBus {
+    pub fn set_timer_control(&mut self, index: usize, value: u16) {
+        let Gba {
+            timers, scheduler, ..
+        } = &mut **self;
+
+        timers.timer[index].set_control(value, scheduler);
     }
 }
@@ -1,19 +1,37 @@
 mod ioreg;
+mod prefetch;
 mod timing;
 
+use prefetch::Prefetch;
+
 use crate::Gba;
 
 use std::ops::{Deref, DerefMut};
-use util::Bus;
+use util::{Access, Bus};
 
 pub struct GbaBus {
     pub bios: Vec<u8>,
     ewram: [u8; 0x02040000 - 0x02000000],
     iwram: [u8; 0x03008000 - 0x03000000],
+
+    waitcnt: u16,                // Raw waitstate control register
+    rom_timing: [(i32, i32); 3], // Non sequential / sequential cycles of WS0 - 2
+    sram_timing: i32,            // Cycles of SRAM accesses
+    pub prefetch: Prefetch,      // Game pak prefetch buffer
     /// Pointer to containing console struct
     pub console: *mut Gba,
 }
 
+// BIOS is loaded by the frontend, and `console` is set by `Gba::init`
+util::snapshot!(GbaBus {
+    ewram,
+    iwram,
+    waitcnt,
+    rom_timing,
+    sram_timing,
+    prefetch
+});
+
 impl Deref for GbaBus {
     type Target = Gba;
     #[inline]
@@ -41,7 +59,7 @@ impl Bus for GbaBus {
             0x04 => self.ioram_load8(offset),
             0x06 => self.ppu.vram.load8(offset),
             0x08..=0x0d => self.cart.rom.load8(offset),
-            0x0e => self.cart.backup.load8(offset),
+            0x0e | 0x0f => self.cart.backup_load8(offset, self.scheduler.now),
             _ => Self::unhandled(true, 1, address),
         }
     }
@@ -58,8 +76,15 @@ impl Bus for GbaBus {
             0x05 => self.ppu.palette.load16(offset),
             0x06 => self.ppu.vram.load16(offset),
             0x07 => self.ppu.oam.load16(offset),
+            0x0d if self.cart.eeprom_mapped(address) => self.cart.eeprom_load16(),
+            0x08 if self.cart.gpio_readable(address) => self.cart.gpio_load16(address),
             0x08..=0x0d => self.cart.rom.load16(offset),
-            0x0e => self.cart.backup.load16(offset),
+            // 8 bit bus, the addressed byte is repeated
+            0x0e | 0x0f => {
+                self.cart
+                    .backup_load8(Self::mirror(address), self.scheduler.now) as u16
+                    * 0x0101
+            }
             _ => Self::unhandled(true, 2, address),
         }
     }
@@ -77,7 +102,11 @@ impl Bus for GbaBus {
             0x06 => self.ppu.vram.load32(offset),
             0x07 => self.ppu.oam.load32(offset),
             0x08..=0x0d => self.cart.rom.load32(offset),
-            0x0e => self.cart.backup.load32(offset),
+            0x0e | 0x0f => {
+                self.cart
+                    .backup_load8(Self::mirror(address), self.scheduler.now) as u32
+                    * 0x01010101
+            }
             _ => Self::unhandled(true, 4, address),
         }
     }
@@ -94,12 +123,15 @@ impl Bus for GbaBus {
             0x04 => self.ioram_store8(offset, value),
             0x05 => self.ppu.palette.store16(offset, hvalue),
             0x06 => self.ppu.vram.store16(offset, hvalue),
-            0x0e => self.cart.backup.store8(offset, value),
+            0x0e | 0x0f => {
+                let now = self.scheduler.now;
+                self.cart.backup_store8(offset, value, now)
+            }
             _ => Self::unhandled(false, 1, address),
         };
     }
 
-    /// Store an halfword in memory, BIOS, ROM, SRAM are inaccessible
+    /// Store an halfword in memory, BIOS, ROM are inaccessible except EEPROM
     fn store16(&mut self, address: usize, value: u16) {
         // Accesses are forced to halfword aligned
         let offset = Self::mirror(address) & !0b1;
@@ -111,11 +143,23 @@ impl Bus for GbaBus {
             0x05 => self.ppu.palette.store16(offset, value),
             0x06 => self.ppu.vram.store16(offset, value),
             0x07 => self.ppu.oam.store16(offset, value),
+            0x0d if self.cart.eeprom_mapped(address) => self.cart.eeprom_store16(value),
+            0x08 if self.cart.gpio_mapped(address) => {
+                let now = self.scheduler.now;
+                self.cart.gpio_store16(address, value, now)
+            }
+            // 8 bit bus, only the addressed byte of the value is written
+            0x0e | 0x0f => {
+                let shift = (address & 1) * 8;
+                let now = self.scheduler.now;
+                self.cart
+                    .backup_store8(Self::mirror(address), (value >> shift) as u8, now)
+            }
             _ => Self::unhandled(false, 2, address),
         };
     }
 
-    /// Store a word in memory, BIOS, ROM, SRAM are inaccessible
+    /// Store a word in memory, BIOS, ROM are inaccessible
     fn store32(&mut self, address: usize, value: u32) {
         // Accesses are forced to be word aligned
         let offset = Self::mirror(address) & !0b11;
@@ -127,9 +171,49 @@ impl Bus for GbaBus {
             0x05 => self.ppu.palette.store32(offset, value),
             0x06 => self.ppu.vram.store32(offset, value),
             0x07 => self.ppu.oam.store32(offset, value),
+            0x0e | 0x0f => {
+                let shift = (address & 3) * 8;
+                let now = self.scheduler.now;
+                self.cart
+                    .backup_store8(Self::mirror(address), (value >> shift) as u8, now)
+            }
             _ => Self::unhandled(false, 4, address),
         };
     }
+
+    #[inline]
+    fn access_cycles(&mut self, address: usize, size: u32, access: Access) -> i32 {
+        let cycles = self.access_timing(address, size, access);
+
+        // Game pak data accesses interrupt prefetching
+        match Self::region(address) {
+            0x08..=0x0d => self.prefetch.stop(),
+            _ => self.step_prefetch(cycles),
+        }
+
+        cycles
+    }
+
+    #[inline]
+    fn fetch_cycles(&mut self, address: usize, size: u32, access: Access) -> i32 {
+        if !matches!(Self::region(address), 0x08..=0x0d) || !self.prefetch.enable {
+            return self.access_cycles(address, size, access);
+        }
+
+        let seq = self.prefetch_timing();
+        match self.prefetch.fetch(address, size, seq) {
+            Some(cycles) => cycles,
+            None => {
+                self.prefetch.restart(address + (1 << size));
+                self.access_timing(address, size, access)
+            }
+        }
+    }
+
+    #[inline]
+    fn idle(&mut self, cycles: i32) {
+        self.step_prefetch(cycles);
+    }
 }
 
 impl GbaBus {
@@ -142,6 +226,10 @@ impl GbaBus {
             // param:      0x05000400 - 0x05000000
             // vram :      0x06018000 - 0x06000000
             // oam  :      0x07000400 - 0x07000000
+            waitcnt: 0,
+            rom_timing: [(5, 3), (5, 5), (5, 9)],
+            sram_timing: 5,
+            prefetch: Prefetch::new(),
             console: std::ptr::null_mut(),
         }
     }
@@ -172,7 +260,7 @@ impl GbaBus {
             }
             0x07 => address % 0x400,
             0x08..=0x0d => address % 0x01000000, // Should be length of rom instead
-            0x0e => address % 0x10000,
+            0x0e | 0x0f => address % 0x10000,
             _ => address,
         }
     }
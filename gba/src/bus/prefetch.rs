@@ -0,0 +1,119 @@
+/// Game pak prefetch buffer, reads sequential ROM halfwords ahead of
+/// the CPU while the game pak bus is otherwise left idle.
+#[derive(Debug)]
+pub struct Prefetch {
+    pub enable: bool,  // WAITCNT bit 14
+    pub active: bool,  // Set after an opcode fetch from ROM
+    pub head: usize,   // Address of the first buffered halfword
+    pub count: u32,    // Number of buffered halfwords, at most 8
+    pub progress: i32, // Cycles spent on the halfword being read
+}
+
+util::snapshot!(Prefetch {
+    enable,
+    active,
+    head,
+    count,
+    progress
+});
+
+impl Prefetch {
+    pub fn new() -> Self {
+        Self {
+            enable: false,
+            active: false,
+            head: 0,
+            count: 0,
+            progress: 0,
+        }
+    }
+
+    /// Discard buffered halfwords and stop prefetching
+    pub fn stop(&mut self) {
+        self.active = false;
+        self.count = 0;
+        self.progress = 0;
+    }
+
+    /// Restart prefetching from `address`
+    pub fn restart(&mut self, address: usize) {
+        self.active = self.enable;
+        self.head = address;
+        self.count = 0;
+        self.progress = 0;
+    }
+
+    /// Let the prefetch unit run for `cycles`,
+    /// `seq` is the cycles of a sequential halfword read.
+    pub fn step(&mut self, cycles: i32, seq: i32) {
+        if !self.active || self.count == 8 {
+            return;
+        }
+
+        self.progress += cycles;
+        while self.progress >= seq && self.count < 8 {
+            self.progress -= seq;
+            self.count += 1;
+        }
+
+        if self.count == 8 {
+            self.progress = 0;
+        }
+    }
+
+    /// Cycles of an opcode fetch of `size` (1 - halfword, 2 - word)
+    /// served by the buffer, or `None` if the opcode is not prefetched.
+    pub fn fetch(&mut self, address: usize, size: u32, seq: i32) -> Option<i32> {
+        let n = size;
+        if !self.active || address != self.head || (self.count == 0 && self.progress == 0) {
+            return None;
+        }
+
+        // Halfwords still being read are waited for, otherwise
+        // prefetching goes on while the opcode is taken from the buffer
+        let cycles = if self.count >= n {
+            self.count -= n;
+            self.step(1, seq);
+            1
+        } else {
+            let wait = (n - self.count) as i32 * seq - self.progress;
+            self.count = 0;
+            self.progress = 0;
+            wait.max(1)
+        };
+
+        self.head += 2 * n as usize;
+        Some(cycles)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sequential_fetch() {
+        let mut prefetch = Prefetch::new();
+        prefetch.enable = true;
+        prefetch.restart(0x08000002);
+
+        // Not yet prefetched
+        assert_eq!(prefetch.fetch(0x08000002, 1, 3), None);
+
+        prefetch.step(7, 3);
+        assert_eq!(prefetch.count, 2);
+        assert_eq!(prefetch.fetch(0x08000002, 1, 3), Some(1));
+        assert_eq!(prefetch.fetch(0x08000004, 1, 3), Some(1));
+
+        // Read while the buffered opcodes were taken
+        assert_eq!(prefetch.fetch(0x08000006, 1, 3), Some(1));
+
+        // Partially read halfword
+        assert_eq!(prefetch.fetch(0x08000008, 1, 3), Some(2));
+
+        // Branch
+        prefetch.step(24, 3);
+        assert_eq!(prefetch.count, 8);
+        assert_eq!(prefetch.fetch(0x08001000, 1, 3), None);
+    }
+}
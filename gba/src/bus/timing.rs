@@ -1,21 +1,100 @@
 use super::GbaBus;
+use util::*;
+
+/// Wait states selected by WAITCNT, SRAM and first access of WS0 - 2
+static FIRST: [i32; 4] = [4, 3, 2, 8];
+
+/// Wait states of sequential accesses of WS0, WS1, WS2
+static SECOND: [[i32; 2]; 3] = [[2, 1], [4, 1], [8, 1]];
 
 impl GbaBus {
-    pub fn access_timing(address: usize, size: u32) -> i32 {
-        // Do not distinguish between sequential and non sequential cycles
-        let timing = match Self::region(address) {
-            0x00 => [1, 1, 1],
-            0x02 => [3, 3, 6],
-            0x03 => [1, 1, 1],
-            0x04 => [1, 1, 1],
-            0x05 => [1, 1, 2],
-            0x06 => [1, 1, 2],
-            0x07 => [1, 1, 1],
-            0x08..=0x0d => [5, 5, 8],
-            // Open bus access timing
-            _ => [1, 1, 1],
-        };
-
-        timing[size as usize]
+    /// Cycles of an access, including wait states
+    pub fn access_timing(&self, address: usize, size: u32, access: Access) -> i32 {
+        let region = Self::region(address);
+
+        match region {
+            0x02 => [3, 3, 6][size as usize],
+            0x05 | 0x06 => [1, 1, 2][size as usize],
+            0x08..=0x0d => {
+                let (n, s) = self.rom_timing[(region - 0x08) / 2];
+
+                // Crossing a 128K boundary forces a non sequential access
+                let seq = access == Access::Seq && address & 0x1ffff != 0;
+                let first = if seq { s } else { n };
+
+                // 32 bit accesses are split into two 16 bit accesses
+                if size == 2 {
+                    first + s
+                } else {
+                    first
+                }
+            }
+            // 8 bit bus, wider accesses are not split
+            0x0e | 0x0f => self.sram_timing,
+            // BIOS, IWRAM, IO, OAM and open bus
+            _ => 1,
+        }
+    }
+
+    /// Cycles of a sequential halfword read by the prefetch unit
+    #[inline]
+    pub fn prefetch_timing(&self) -> i32 {
+        let region = Self::region(self.prefetch.head);
+        match region {
+            0x08..=0x0d => self.rom_timing[(region - 0x08) / 2].1,
+            _ => 1,
+        }
+    }
+
+    #[inline]
+    pub fn step_prefetch(&mut self, cycles: i32) {
+        let seq = self.prefetch_timing();
+        self.prefetch.step(cycles, seq);
+    }
+
+    #[inline]
+    pub fn get_waitcnt(&self) -> u16 {
+        self.waitcnt
+    }
+
+    #[inline]
+    pub fn set_waitcnt(&mut self, value: u16) {
+        // Bit 15, game pak type flag is read only
+        self.waitcnt = value & 0x5fff;
+
+        self.sram_timing = 1 + FIRST[value.bits(1, 0) as usize];
+        for i in 0..3 {
+            let first = FIRST[value.bits(3 + i * 3, 2 + i * 3) as usize];
+            let second = SECOND[i as usize][value.bit(4 + i * 3) as usize];
+            self.rom_timing[i as usize] = (1 + first, 1 + second);
+        }
+
+        self.prefetch.enable = value.bit(14);
+        if !self.prefetch.enable {
+            self.prefetch.stop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn waitcnt() {
+        let mut bus = GbaBus::new();
+        assert_eq!(bus.access_timing(0x08000000, 1, Access::NonSeq), 5);
+        assert_eq!(bus.access_timing(0x08000002, 2, Access::Seq), 6);
+        assert_eq!(bus.access_timing(0x0e000000, 2, Access::NonSeq), 5);
+
+        // WS0 3/1, WS2 8/1, SRAM 8
+        bus.set_waitcnt(0b0111_0001_0111);
+        assert_eq!(bus.access_timing(0x08000000, 2, Access::NonSeq), 6);
+        assert_eq!(bus.access_timing(0x08000004, 2, Access::Seq), 4);
+        assert_eq!(bus.access_timing(0x0d000000, 1, Access::NonSeq), 9);
+        assert_eq!(bus.access_timing(0x0e000000, 0, Access::NonSeq), 9);
+
+        // Sequential access across a 128K boundary
+        assert_eq!(bus.access_timing(0x08020000, 1, Access::Seq), 4);
     }
 }
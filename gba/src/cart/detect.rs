@@ -0,0 +1,100 @@
+//! Backup type detection, from library ID strings Nintendo's SDK
+//! places in the ROM, or from a list of known games
+
+use super::flash::Chip;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BackupType {
+    Sram,
+    Eeprom,      // 512B or 8KB, size is detected on first access
+    Flash(Chip), // 64KB or 128KB flash, depending on the chip
+}
+
+use BackupType::*;
+
+/// ID strings are word aligned and followed by a version number
+static IDS: [(&[u8], BackupType); 6] = [
+    (b"EEPROM_V", Eeprom),
+    (b"SRAM_V", Sram),
+    (b"SRAM_F_V", Sram),
+    (b"FLASH_V", Flash(Chip::Panasonic)),
+    (b"FLASH512_V", Flash(Chip::Panasonic)),
+    (b"FLASH1M_V", Flash(Chip::Macronix128)),
+];
+
+/// Games whose ID strings are missing or misleading, by game code
+static DATABASE: [(&str, BackupType); 5] = [
+    ("AXVE", Flash(Chip::Macronix128)), // Pokemon Ruby
+    ("AXPE", Flash(Chip::Macronix128)), // Pokemon Sapphire
+    ("BPEE", Flash(Chip::Macronix128)), // Pokemon Emerald
+    ("BPRE", Flash(Chip::Macronix128)), // Pokemon FireRed
+    ("BPGE", Flash(Chip::Macronix128)), // Pokemon LeafGreen
+];
+
+impl BackupType {
+    /// Look up the game in the database, then scan the ROM for ID strings
+    pub fn detect(rom: &[u8]) -> Option<Self> {
+        let code = rom.get(0xac..0xb0)?;
+        for &(c, kind) in DATABASE.iter() {
+            if c.as_bytes() == code {
+                return Some(kind);
+            }
+        }
+
+        for offset in (0..rom.len()).step_by(4) {
+            for &(id, kind) in IDS.iter() {
+                if rom[offset..].starts_with(id) {
+                    return Some(kind);
+                }
+            }
+        }
+
+        None
+    }
+}
+
+impl FromStr for BackupType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "sram" => Ok(Sram),
+            "eeprom" => Ok(Eeprom),
+            "flash" | "flash64" | "flash512" | "panasonic" => Ok(Flash(Chip::Panasonic)),
+            "flash128" | "flash1m" | "macronix128" => Ok(Flash(Chip::Macronix128)),
+            "sst" => Ok(Flash(Chip::Sst)),
+            "macronix64" => Ok(Flash(Chip::Macronix64)),
+            "atmel" => Ok(Flash(Chip::Atmel)),
+            "sanyo" => Ok(Flash(Chip::Sanyo)),
+            _ => Err(format!("Unknown backup type {}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn id_string() {
+        let mut rom = vec![0; 0x1000];
+        assert_eq!(BackupType::detect(&rom), None);
+
+        rom[0x800..0x80d].copy_from_slice(b"FLASH1M_V103\0");
+        assert_eq!(BackupType::detect(&rom), Some(Flash(Chip::Macronix128)));
+
+        // Not word aligned
+        let mut rom = vec![0; 0x1000];
+        rom[0x802..0x80a].copy_from_slice(b"SRAM_V11");
+        assert_eq!(BackupType::detect(&rom), None);
+    }
+
+    #[test]
+    fn database() {
+        let mut rom = vec![0; 0x1000];
+        rom[0xac..0xb0].copy_from_slice(b"BPEE");
+        rom[0x800..0x808].copy_from_slice(b"SRAM_V11");
+        assert_eq!(BackupType::detect(&rom), Some(Flash(Chip::Macronix128)));
+    }
+}
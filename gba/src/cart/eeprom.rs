@@ -0,0 +1,191 @@
+use std::cell::Cell;
+use std::io;
+use util::{snapshot, Snapshot};
+
+/// Serial EEPROM, accessed one bit per halfword through DMA 3
+pub struct Eeprom {
+    data: Vec<u8>,        // Large enough for both chip sizes
+    pub width: u32,       // Address width in bits, 6 - 512B, 14 - 8KB, 0 - unknown
+    state: State,         // Progress of current request
+    value: u64,           // Bits received in current state
+    bits: u32,            // Number of bits received in current state
+    address: usize,       // Byte offset of the addressed 64 bit block
+    output: u64,          // Block being read out
+    remaining: Cell<u32>, // Bits left to be read, including 4 dummy bits
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+    Command,       // Waiting for 2 bit request type
+    Address(bool), // Receiving address, true for read requests
+    Data,          // Receiving 64 bits to be written
+    End(bool),     // Waiting for the terminating bit
+}
+
+impl Snapshot for State {
+    fn save_state(&self, out: &mut Vec<u8>) {
+        let (tag, value) = match *self {
+            State::Command => (0, 0),
+            State::Address(read) => (1, read as u8),
+            State::Data => (2, 0),
+            State::End(read) => (3, read as u8),
+        };
+        out.extend_from_slice(&[tag, value]);
+    }
+
+    fn load_state(&mut self, input: &mut &[u8]) -> io::Result<()> {
+        let bytes = snapshot::take(input, 2)?;
+        *self = match (bytes[0], bytes[1]) {
+            (0, 0) => State::Command,
+            (1, read @ 0..=1) => State::Address(read == 1),
+            (2, 0) => State::Data,
+            (3, read @ 0..=1) => State::End(read == 1),
+            _ => return Err(snapshot::invalid("EEPROM state")),
+        };
+        Ok(())
+    }
+}
+
+util::snapshot!(Eeprom {
+    data,
+    width,
+    state,
+    value,
+    bits,
+    address,
+    output,
+    remaining
+});
+
+impl Eeprom {
+    pub fn new() -> Self {
+        Self {
+            data: vec![0xff; 0x2000],
+            width: 0,
+            state: State::Command,
+            value: 0,
+            bits: 0,
+            address: 0,
+            output: 0,
+            remaining: Cell::new(0),
+        }
+    }
+
+    pub fn data(&self) -> &[u8] {
+        match self.width {
+            6 => &self.data[..0x200],
+            _ => &self.data,
+        }
+    }
+
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+
+    /// Infer address width from the number of halfwords DMA writes.
+    /// Read requests are 9 or 17 bits, writes 73 or 81 bits long.
+    pub fn set_transfer_length(&mut self, length: u16) {
+        match length {
+            9 | 73 => self.width = 6,
+            17 | 81 => self.width = 14,
+            _ => (),
+        }
+    }
+
+    /// Read the next bit, 1 if ready for a new request
+    pub fn read(&self) -> u16 {
+        let remaining = self.remaining.get();
+        if remaining == 0 {
+            return 1;
+        }
+
+        self.remaining.set(remaining - 1);
+        if remaining > 64 {
+            0
+        } else {
+            (self.output >> (remaining - 1)) as u16 & 1
+        }
+    }
+
+    /// Receive a bit of a request
+    pub fn write(&mut self, value: u16) {
+        use State::*;
+
+        self.value = self.value << 1 | (value & 1) as u64;
+        self.bits += 1;
+
+        // Assume the larger chip if no DMA has told otherwise
+        let width = if self.width == 0 { 14 } else { self.width };
+
+        let next = match self.state {
+            Command if self.bits == 2 => match self.value {
+                0b11 => Address(true),
+                0b10 => Address(false),
+                _ => Command,
+            },
+            Address(read) if self.bits == width => {
+                // Only lower 10 bits are used by the 8KB chip
+                self.address = (self.value as usize & 0x3ff) * 8;
+                if read {
+                    End(true)
+                } else {
+                    Data
+                }
+            }
+            Data if self.bits == 64 => {
+                let block = &mut self.data[self.address..self.address + 8];
+                block.copy_from_slice(&self.value.to_be_bytes());
+                End(false)
+            }
+            End(read) => {
+                if read {
+                    let block = &self.data[self.address..self.address + 8];
+                    self.output = u64::from_be_bytes(block.try_into().unwrap());
+                    self.remaining.set(68);
+                }
+                Command
+            }
+            _ => return,
+        };
+
+        self.state = next;
+        self.value = 0;
+        self.bits = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn send(eeprom: &mut Eeprom, value: u64, bits: u32) {
+        for i in (0..bits).rev() {
+            eeprom.write((value >> i) as u16 & 1);
+        }
+    }
+
+    #[test]
+    fn write_read() {
+        let mut eeprom = Eeprom::new();
+        eeprom.set_transfer_length(73);
+
+        // Write request, address 3, data, terminating bit
+        send(&mut eeprom, 0b10, 2);
+        send(&mut eeprom, 3, 6);
+        send(&mut eeprom, 0x0123456789abcdef, 64);
+        send(&mut eeprom, 0, 1);
+        assert_eq!(eeprom.data()[24..32], 0x0123456789abcdefu64.to_be_bytes());
+        assert_eq!(eeprom.data().len(), 0x200);
+
+        // Read request
+        send(&mut eeprom, 0b11, 2);
+        send(&mut eeprom, 3, 6);
+        send(&mut eeprom, 0, 1);
+
+        let bits: Vec<u16> = (0..68).map(|_| eeprom.read()).collect();
+        assert_eq!(bits[..4], [0; 4]);
+        let value = bits[4..].iter().fold(0u64, |a, &b| a << 1 | b as u64);
+        assert_eq!(value, 0x0123456789abcdef);
+        assert_eq!(eeprom.read(), 1);
+    }
+}
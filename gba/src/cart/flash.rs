@@ -1,32 +1,130 @@
+use std::cell::Cell;
+
+/// Flash chip models found in game paks
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Chip {
+    Sst,         // SST 39VF512, 64KB
+    Macronix64,  // Macronix MX29L512, 64KB
+    Panasonic,   // Panasonic MN63F805MNP, 64KB
+    Atmel,       // Atmel AT29LV512, 64KB, written in 128 byte pages
+    Macronix128, // Macronix MX29L010, 128KB
+    Sanyo,       // Sanyo LE26FV10N1TS, 128KB
+}
+
+/// Cycles in a microsecond, roughly
+const US: u64 = 17;
+
+impl Chip {
+    /// Manufacturer and device ID
+    pub fn id(self) -> (u8, u8) {
+        match self {
+            Chip::Sst => (0xbf, 0xd4),
+            Chip::Macronix64 => (0xc2, 0x1c),
+            Chip::Panasonic => (0x32, 0x1b),
+            Chip::Atmel => (0x1f, 0x3d),
+            Chip::Macronix128 => (0xc2, 0x09),
+            Chip::Sanyo => (0x62, 0x13),
+        }
+    }
+
+    pub fn size(self) -> usize {
+        match self {
+            Chip::Macronix128 | Chip::Sanyo => 128 * 1024,
+            _ => 64 * 1024,
+        }
+    }
+
+    /// Typical busy durations in cycles of byte program (page write
+    /// for Atmel), sector erase and chip erase
+    pub fn timing(self) -> (u64, u64, u64) {
+        match self {
+            Chip::Sst => (20 * US, 25_000 * US, 100_000 * US),
+            Chip::Macronix64 | Chip::Macronix128 => (10 * US, 100_000 * US, 400_000 * US),
+            Chip::Panasonic => (20 * US, 50_000 * US, 200_000 * US),
+            Chip::Atmel => (5_000 * US, 0, 20_000 * US),
+            Chip::Sanyo => (30 * US, 50_000 * US, 200_000 * US),
+        }
+    }
+}
+
 pub struct Flash {
     flash: Vec<u8>,
+    pub chip: Chip,
     command: Vec<(usize, u8)>,
     bank: usize,
     state: u32,
     id: bool,
     erase: bool,
+    page: u32,      // Remaining bytes of an Atmel page write
+    busy: u64,      // Timestamp when current program / erase finishes
+    status: u8,     // Read while busy, bit 7 is inverted data
+    now: Cell<u64>, // Time of last access
 }
 
+// Chip model comes from the cartridge
+util::snapshot!(Flash {
+    flash,
+    command,
+    bank,
+    state,
+    id,
+    erase,
+    page,
+    busy,
+    status,
+    now
+});
+
 impl Flash {
-    pub fn new() -> Self {
+    pub fn new(chip: Chip) -> Self {
         Self {
-            flash: vec![0; 128 * 1024],
+            flash: vec![0xff; chip.size()],
+            chip,
             command: Vec::new(),
             bank: 0,
             state: 0,
             id: false,
             erase: false,
+            page: 0,
+            busy: 0,
+            status: 0,
+            now: Cell::new(0),
         }
     }
+
+    pub fn data(&self) -> &[u8] {
+        &self.flash
+    }
+
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        &mut self.flash
+    }
+
+    /// Called before every access, with the current timestamp
+    #[inline]
+    pub fn tick(&self, now: u64) {
+        self.now.set(now);
+    }
+
+    /// Start programming / erasing taking `duration` cycles
+    fn start_busy(&mut self, duration: u64, value: u8) {
+        self.busy = self.now.get() + duration;
+        self.status = !value & 0x80;
+    }
 }
 
 impl util::Bus for Flash {
     fn load8(&self, address: usize) -> u8 {
+        if self.now.get() < self.busy {
+            return self.status;
+        }
+
         if self.id {
+            let (manufacturer, device) = self.chip.id();
             if address == 0 {
-                return 0xc2;
+                return manufacturer;
             } else if address == 1 {
-                return 0x09;
+                return device;
             }
         }
 
@@ -34,6 +132,20 @@ impl util::Bus for Flash {
     }
 
     fn store8(&mut self, address: usize, value: u8) {
+        if self.now.get() < self.busy {
+            return;
+        }
+
+        // Atmel chips program a whole page after the write command
+        if self.page > 0 {
+            self.flash[self.bank + address] = value;
+            self.page -= 1;
+            if self.page == 0 {
+                self.start_busy(self.chip.timing().0, value);
+            }
+            return;
+        }
+
         if (self.state, address, value) == (0, 0x5555, 0xaa) {
             self.state = 1
         } else if (self.state, address, value) == (1, 0x2aaa, 0x55) {
@@ -41,6 +153,7 @@ impl util::Bus for Flash {
         } else if self.state == 2 {
             self.command.push((address, value));
 
+            let (program, sector, chip) = self.chip.timing();
             match self.command.as_slice() {
                 // Enter ID mode
                 [(0x5555, 0x90)] => {
@@ -58,33 +171,36 @@ impl util::Bus for Flash {
                     return;
                 }
                 // Erase entire chip
-                [(0x5555, 0x10)] => {
-                    if self.erase {
-                        self.flash.iter_mut().for_each(|b| *b = 0xff);
-                    }
+                [(0x5555, 0x10)] if self.erase => {
+                    self.flash.iter_mut().for_each(|b| *b = 0xff);
+                    self.start_busy(chip, 0xff);
                 }
-                // Erase 4 KB sector
-                [(n, 0x30)] => {
-                    if self.erase {
-                        let a = self.bank + *n;
-                        self.flash[a..a + 4096].iter_mut().for_each(|b| *b = 0xff);
-                    }
+                // Erase 4 KB sector, Atmel chips have no sector erase
+                [(n, 0x30)] if self.erase && self.chip != Chip::Atmel => {
+                    let a = self.bank + (*n & 0xf000);
+                    self.flash[a..a + 4096].iter_mut().for_each(|b| *b = 0xff);
+                    self.start_busy(sector, 0xff);
                 }
                 // Look ahead
                 [(0x5555, 0xa0)] => {
                     return;
                 }
-                // Single data write
+                // Single data write, or first byte of an Atmel page
                 [(0x5555, 0xa0), (addr, value)] => {
                     self.flash[self.bank + addr] = *value;
+                    if self.chip == Chip::Atmel {
+                        self.page = 127 - (*addr as u32 & 0x7f);
+                    } else {
+                        self.start_busy(program, *value);
+                    }
                 }
                 // Look ahead
-                [(0x5555, 0xb0)] => {
+                [(0x5555, 0xb0)] if self.flash.len() > 0x10000 => {
                     return;
                 }
-                // Bank switching
+                // Bank switching, only on 128KB chips
                 [(0x5555, 0xb0), (0, bank)] => {
-                    self.bank = (*bank as usize) << 16;
+                    self.bank = (*bank as usize & 1) << 16;
                 }
                 _ => {}
             }
@@ -98,3 +214,49 @@ impl util::Bus for Flash {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use util::Bus;
+
+    fn command(flash: &mut Flash, value: u8) {
+        flash.store8(0x5555, 0xaa);
+        flash.store8(0x2aaa, 0x55);
+        flash.store8(0x5555, value);
+    }
+
+    #[test]
+    fn chip_id() {
+        let mut flash = Flash::new(Chip::Sst);
+        command(&mut flash, 0x90);
+        assert_eq!((flash.load8(0), flash.load8(1)), (0xbf, 0xd4));
+        command(&mut flash, 0xf0);
+        assert_eq!(flash.load8(0), 0xff);
+    }
+
+    #[test]
+    fn program_busy() {
+        let mut flash = Flash::new(Chip::Panasonic);
+        command(&mut flash, 0xa0);
+        flash.store8(0x10, 0x42);
+
+        // Bit 7 of data is inverted until done
+        assert_eq!(flash.load8(0x10), 0x80);
+        flash.tick(Chip::Panasonic.timing().0);
+        assert_eq!(flash.load8(0x10), 0x42);
+    }
+
+    #[test]
+    fn bank_switch() {
+        let mut flash = Flash::new(Chip::Panasonic);
+        command(&mut flash, 0xb0);
+        flash.store8(0, 1);
+        assert_eq!(flash.bank, 0);
+
+        let mut flash = Flash::new(Chip::Sanyo);
+        command(&mut flash, 0xb0);
+        flash.store8(0, 1);
+        assert_eq!(flash.bank, 0x10000);
+    }
+}
@@ -0,0 +1,119 @@
+//! General purpose I/O port of game paks, mapped to 0x080000c4 - 0x080000c9
+//! and used to talk to extra hardware on the cartridge
+
+use super::rtc::{Clock, Rtc};
+use super::sensor::{Gyro, Solar};
+
+/// Hardware behind the GPIO port
+const RTC: u8 = 1 << 0;
+const SOLAR: u8 = 1 << 1;
+const GYRO: u8 = 1 << 2;
+const RUMBLE: u8 = 1 << 3;
+
+/// Known carts by the first 3 letters of game code, the last is the region
+static GAMES: [(&[u8], u8); 8] = [
+    (b"AXV", RTC),           // Pokemon Ruby
+    (b"AXP", RTC),           // Pokemon Sapphire
+    (b"BPE", RTC),           // Pokemon Emerald
+    (b"U3I", RTC | SOLAR),   // Boktai
+    (b"U32", RTC | SOLAR),   // Boktai 2
+    (b"U33", RTC | SOLAR),   // Boktai 3
+    (b"RZW", GYRO | RUMBLE), // WarioWare Twisted
+    (b"V49", RUMBLE),        // Drill Dozer
+];
+
+pub struct Gpio {
+    data: u8,           // Pins last written by the GBA
+    direction: u8,      // 1 - pin is an output of the GBA, 0 - input
+    pub readable: bool, // Registers read back as ROM contents if unset
+    pub rtc: Option<Rtc>,
+    pub solar: Option<Solar>,
+    pub gyro: Option<Gyro>,
+    rumble: bool, // Pin 3 drives a rumble motor
+}
+
+util::snapshot!(Gpio {
+    data,
+    direction,
+    readable,
+    rtc,
+    solar,
+    gyro
+});
+
+impl Gpio {
+    fn new(devices: u8) -> Self {
+        Self {
+            data: 0,
+            direction: 0,
+            readable: false,
+            rtc: (devices & RTC != 0).then(|| Rtc::new(Clock::Host)),
+            solar: (devices & SOLAR != 0).then(Solar::new),
+            gyro: (devices & GYRO != 0).then(Gyro::new),
+            rumble: devices & RUMBLE != 0,
+        }
+    }
+
+    /// Look up the game code, then the RTC library ID string
+    pub fn detect(rom: &[u8]) -> Option<Self> {
+        let code = rom.get(0xac..0xaf)?;
+        if let Some(&(_, devices)) = GAMES.iter().find(|(c, _)| *c == code) {
+            return Some(Self::new(devices));
+        }
+
+        let rtc = (0..rom.len())
+            .step_by(4)
+            .any(|i| rom[i..].starts_with(b"SIIRTC_V"));
+        rtc.then(|| Self::new(RTC))
+    }
+
+    /// Pin state, inputs are driven by the devices
+    fn pins(&self) -> u8 {
+        let mut input = 0;
+        if let Some(rtc) = &self.rtc {
+            input |= rtc.read();
+        }
+        if let Some(solar) = &self.solar {
+            input |= solar.read();
+        }
+        if let Some(gyro) = &self.gyro {
+            input |= gyro.read();
+        }
+
+        (self.data & self.direction | input & !self.direction) & 0xf
+    }
+
+    /// Whether the rumble motor is on
+    pub fn rumble(&self) -> bool {
+        self.rumble && self.data & self.direction & 8 != 0
+    }
+
+    pub fn load16(&self, address: usize) -> u16 {
+        match address & 0xf {
+            0x4 => self.pins() as u16,
+            0x6 => self.direction as u16,
+            0x8 => self.readable as u16,
+            _ => 0,
+        }
+    }
+
+    pub fn store16(&mut self, address: usize, value: u16, now: u64) {
+        match address & 0xf {
+            0x4 => self.data = value as u8 & 0xf,
+            0x6 => self.direction = value as u8 & 0xf,
+            0x8 => self.readable = value & 1 != 0,
+            _ => return,
+        }
+
+        let output = self.data & self.direction;
+        if let Some(rtc) = &mut self.rtc {
+            rtc.write(output, now);
+        }
+        if let Some(solar) = &mut self.solar {
+            solar.write(output);
+        }
+        if let Some(gyro) = &mut self.gyro {
+            gyro.write(output);
+        }
+    }
+}
@@ -1,42 +1,315 @@
 //! Module for handling gamepak / cartridge functionalities
 //! Backup / RTC, etc.
 
+mod detect;
+mod eeprom;
 mod flash;
+mod gpio;
+mod rtc;
+mod sensor;
 use flash::Flash;
-use util::Bus;
+use gpio::Gpio;
+use sensor::Tilt;
+use std::io;
+use std::path::PathBuf;
+use util::{snapshot, Bus, Snapshot};
 use Backup::*;
 
+pub use detect::BackupType;
+pub use flash::Chip;
+pub use rtc::Clock;
+
+/// Frames without backup writes before the save file is updated
+const SAVE_DELAY: u32 = 60;
+
 pub enum Backup {
     Flash(flash::Flash),
     Sram(Vec<u8>),
+    Eeprom(eeprom::Eeprom),
 }
 
+/// Size of SRAM backup, mirrored across the 64K backup region
+pub const SRAM_SIZE: usize = 0x8000;
+
+/// Backup chips sit on an 8 bit bus, wider accesses are done by `GbaBus`
 impl Bus for Backup {
     fn load8(&self, address: usize) -> u8 {
         match self {
             Flash(f) => f.load8(address & 0xffff),
-            _ => todo!(),
+            Sram(s) => s[address % SRAM_SIZE],
+            // Mapped to the top of ROM space instead
+            Eeprom(_) => 0xff,
         }
     }
 
     fn store8(&mut self, address: usize, value: u8) {
         match self {
             Flash(f) => f.store8(address & 0xffff, value),
-            _ => todo!(),
+            Sram(s) => s[address % SRAM_SIZE] = value,
+            Eeprom(_) => (),
         }
     }
 }
 
+/// The chip is given by the cartridge, only its contents are restored
+impl Snapshot for Backup {
+    fn save_state(&self, out: &mut Vec<u8>) {
+        let (tag, state): (u8, &dyn Snapshot) = match self {
+            Flash(f) => (0, f),
+            Sram(s) => (1, s),
+            Eeprom(e) => (2, e),
+        };
+        tag.save_state(out);
+        state.save_state(out);
+    }
+
+    fn load_state(&mut self, input: &mut &[u8]) -> io::Result<()> {
+        let tag = snapshot::take(input, 1)?[0];
+        match (tag, self) {
+            (0, Flash(f)) => f.load_state(input),
+            (1, Sram(s)) => s.load_state(input),
+            (2, Eeprom(e)) => e.load_state(input),
+            _ => Err(snapshot::invalid("backup type")),
+        }
+    }
+}
+
+impl Backup {
+    pub fn new(kind: BackupType) -> Self {
+        match kind {
+            BackupType::Sram => Sram(vec![0xff; SRAM_SIZE]),
+            BackupType::Eeprom => Eeprom(eeprom::Eeprom::new()),
+            BackupType::Flash(chip) => Flash(Flash::new(chip)),
+        }
+    }
+
+    /// Raw backup contents, as stored in save files
+    pub fn data(&self) -> &[u8] {
+        match self {
+            Flash(f) => f.data(),
+            Sram(s) => s,
+            Eeprom(e) => e.data(),
+        }
+    }
+
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        match self {
+            Flash(f) => f.data_mut(),
+            Sram(s) => s,
+            Eeprom(e) => e.data_mut(),
+        }
+    }
+
+    /// Restore contents from a save file
+    pub fn load(&mut self, data: &[u8]) {
+        // Save file size tells the EEPROM address width
+        if let Eeprom(e) = self {
+            e.width = if data.len() == 0x200 { 6 } else { 14 };
+        }
+
+        let backup = self.data_mut();
+        let len = data.len().min(backup.len());
+        backup[..len].copy_from_slice(&data[..len]);
+    }
+}
+
 pub struct Cart {
     pub rom: Vec<u8>,
     pub backup: Backup,
+    pub gpio: Option<Gpio>,    // Present on carts with extra hardware
+    pub tilt: Option<Tilt>,    // Tilt sensor mapped over SRAM
+    pub save: Option<PathBuf>, // Battery save file
+    pub dirty: bool,           // Backup written since last save
+    pub idle: u32,             // Frames since last backup write
+    pub checksum: u32,         // CRC32 of the ROM, identifies save states
+}
+
+impl Snapshot for Cart {
+    fn save_state(&self, out: &mut Vec<u8>) {
+        self.backup.save_state(out);
+        self.gpio.save_state(out);
+        self.tilt.save_state(out);
+    }
+
+    /// Restored backup contents are written to the save file later
+    fn load_state(&mut self, input: &mut &[u8]) -> io::Result<()> {
+        self.backup.load_state(input)?;
+        self.gpio.load_state(input)?;
+        self.tilt.load_state(input)?;
+        self.dirty = true;
+        self.idle = 0;
+        Ok(())
+    }
 }
 
 impl Cart {
+    /// Backup type is detected from the ROM, defaulting to SRAM
     pub fn with_rom(rom: Vec<u8>) -> Self {
+        let kind = BackupType::detect(&rom).unwrap_or(BackupType::Sram);
+        Self::with_backup(rom, kind)
+    }
+
+    pub fn with_backup(rom: Vec<u8>, kind: BackupType) -> Self {
         Self {
+            backup: Backup::new(kind),
+            gpio: Gpio::detect(&rom),
+            tilt: Tilt::detect(&rom),
+            checksum: util::crc32(&rom),
             rom,
-            backup: Flash(Flash::new()),
+            save: None,
+            dirty: false,
+            idle: 0,
+        }
+    }
+
+    /// Read from the backup chip, `now` is needed for flash busy status
+    pub fn backup_load8(&self, address: usize, now: u64) -> u8 {
+        if let Some(tilt) = self.tilt.as_ref().filter(|_| Tilt::mapped(address)) {
+            return tilt.load8(address);
+        }
+        if let Flash(f) = &self.backup {
+            f.tick(now);
+        }
+        self.backup.load8(address)
+    }
+
+    /// Write to the backup chip, the save file is updated later
+    pub fn backup_store8(&mut self, address: usize, value: u8, now: u64) {
+        if let Some(tilt) = self.tilt.as_mut().filter(|_| Tilt::mapped(address)) {
+            return tilt.store8(address, value);
+        }
+        if let Flash(f) = &self.backup {
+            f.tick(now);
+        }
+        self.backup.store8(address, value);
+        self.dirty = true;
+        self.idle = 0;
+    }
+
+    /// EEPROM is accessed at 0x0d000000 - 0x0dffffff, or only
+    /// the top 256 bytes of it if the ROM is larger than 16MB
+    #[inline]
+    pub fn eeprom_mapped(&self, address: usize) -> bool {
+        matches!(self.backup, Eeprom(_)) && (self.rom.len() <= 0x1000000 || address >= 0x0dffff00)
+    }
+
+    pub fn eeprom_load16(&self) -> u16 {
+        match &self.backup {
+            Eeprom(e) => e.read(),
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn eeprom_store16(&mut self, value: u16) {
+        if let Eeprom(e) = &mut self.backup {
+            e.write(value);
+            self.dirty = true;
+            self.idle = 0;
+        }
+    }
+
+    /// GPIO registers are mapped over ROM at 0x080000c4 - 0x080000c9
+    #[inline]
+    pub fn gpio_mapped(&self, address: usize) -> bool {
+        (0x080000c4..0x080000ca).contains(&address) && self.gpio.is_some()
+    }
+
+    /// ROM contents are read instead unless the port is made readable
+    #[inline]
+    pub fn gpio_readable(&self, address: usize) -> bool {
+        self.gpio_mapped(address) && self.gpio.as_ref().is_some_and(|g| g.readable)
+    }
+
+    pub fn gpio_load16(&self, address: usize) -> u16 {
+        self.gpio.as_ref().map_or(0, |g| g.load16(address))
+    }
+
+    pub fn gpio_store16(&mut self, address: usize, value: u16, now: u64) {
+        if let Some(g) = &mut self.gpio {
+            g.store16(address, value, now);
+        }
+    }
+
+    /// Select the time source of the cartridge RTC, if any
+    pub fn set_clock(&mut self, clock: Clock) {
+        if let Some(rtc) = self.gpio.as_mut().and_then(|g| g.rtc.as_mut()) {
+            rtc.clock = clock;
         }
     }
+
+    /// Whether the rumble motor is on
+    pub fn rumble(&self) -> bool {
+        self.gpio.as_ref().is_some_and(|g| g.rumble())
+    }
+
+    /// Load backup contents from `path` and save to it afterwards.
+    /// A missing file is created on the first save.
+    pub fn attach_save(&mut self, path: impl Into<PathBuf>) -> io::Result<()> {
+        let path = path.into();
+
+        match std::fs::read(&path) {
+            Ok(data) => self.backup.load(&data),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => (),
+            Err(e) => return Err(e),
+        }
+
+        self.save = Some(path);
+        Ok(())
+    }
+
+    /// Write backup contents to the save file, if changed
+    pub fn flush_save(&mut self) -> io::Result<()> {
+        if let (Some(path), true) = (&self.save, self.dirty) {
+            std::fs::write(path, self.backup.data())?;
+            self.dirty = false;
+        }
+
+        Ok(())
+    }
+
+    /// Called every frame, saves once the game stops writing the backup
+    pub fn update_save(&mut self) -> io::Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+
+        self.idle += 1;
+        if self.idle < SAVE_DELAY {
+            return Ok(());
+        }
+
+        self.flush_save()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sram_mirror() {
+        let mut backup = Sram(vec![0; SRAM_SIZE]);
+        backup.store8(0x8001, 0xab);
+        assert_eq!(backup.load8(0x0001), 0xab);
+        assert_eq!(backup.load8(0xffff), 0);
+    }
+
+    #[test]
+    fn save_roundtrip() {
+        let path = std::env::temp_dir().join("gamebar_save_roundtrip.sav");
+        let _ = std::fs::remove_file(&path);
+
+        let mut cart = Cart::with_backup(Vec::new(), BackupType::Flash(Chip::Macronix128));
+        cart.attach_save(&path).unwrap();
+        cart.backup.data_mut()[0x1234] = 0x56;
+        cart.dirty = true;
+        cart.flush_save().unwrap();
+
+        let mut other = Cart::with_backup(Vec::new(), BackupType::Flash(Chip::Macronix128));
+        other.attach_save(&path).unwrap();
+        assert_eq!(other.backup.data()[0x1234], 0x56);
+        assert_eq!(std::fs::read(&path).unwrap().len(), 128 * 1024);
+
+        std::fs::remove_file(&path).unwrap();
+    }
 }
@@ -0,0 +1,358 @@
+//! Seiko S-3511 real time clock, a 3 wire serial device behind the GPIO port
+
+use std::io;
+use std::time::{SystemTime, UNIX_EPOCH};
+use util::{snapshot, Snapshot};
+
+/// GPIO pins used by the RTC
+const SCK: u8 = 1 << 0; // Serial clock
+const SIO: u8 = 1 << 1; // Serial data
+const CS: u8 = 1 << 2; // Chip select
+
+/// Emulated cycles per second
+const FREQUENCY: u64 = 16 * 1024 * 1024;
+
+/// Seconds from Unix epoch to 2000-01-01, the date a reset sets
+const Y2K: i64 = 946684800;
+
+/// Source of the current time
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Clock {
+    Host,       // Host system clock in UTC
+    Fixed(i64), // Unix time at power on, advanced by emulated time only
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+    Idle,         // Chip select low
+    Command,      // Receiving command byte
+    Read(usize),  // Sending parameter bytes of a command
+    Write(usize), // Receiving parameter bytes of a command
+    Done,         // Waiting for chip select to go low
+}
+
+impl Snapshot for State {
+    fn save_state(&self, out: &mut Vec<u8>) {
+        let (tag, value) = match *self {
+            State::Idle => (0, 0),
+            State::Command => (1, 0),
+            State::Read(command) => (2, command as u8),
+            State::Write(command) => (3, command as u8),
+            State::Done => (4, 0),
+        };
+        out.extend_from_slice(&[tag, value]);
+    }
+
+    fn load_state(&mut self, input: &mut &[u8]) -> io::Result<()> {
+        let bytes = snapshot::take(input, 2)?;
+        *self = match (bytes[0], bytes[1]) {
+            (0, 0) => State::Idle,
+            (1, 0) => State::Command,
+            (2, command @ 0..=7) => State::Read(command as usize),
+            (3, command @ 0..=7) => State::Write(command as usize),
+            (4, 0) => State::Done,
+            _ => return Err(snapshot::invalid("RTC state")),
+        };
+        Ok(())
+    }
+}
+
+pub struct Rtc {
+    pub clock: Clock,
+    offset: i64,     // Seconds added to the clock by the game setting time
+    status: u8,      // Status register, bit 6 - 24 hour mode
+    pins: u8,        // Pin state last written
+    state: State,    // Progress of current transfer
+    value: u8,       // Byte being received
+    bits: u32,       // Bits transferred of current byte
+    buffer: [u8; 7], // Parameter bytes
+    index: usize,    // Parameter byte being transferred
+    output: u8,      // Value of SIO driven by the RTC
+}
+
+// Clock source is chosen by the frontend
+util::snapshot!(Rtc {
+    offset,
+    status,
+    pins,
+    state,
+    value,
+    bits,
+    buffer,
+    index,
+    output
+});
+
+impl Rtc {
+    pub fn new(clock: Clock) -> Self {
+        Self {
+            clock,
+            offset: 0,
+            status: 0x40,
+            pins: 0,
+            state: State::Idle,
+            value: 0,
+            bits: 0,
+            buffer: [0; 7],
+            index: 0,
+            output: 0,
+        }
+    }
+
+    /// Unix time as seen by the game at cycle `now`
+    fn time(&self, now: u64) -> i64 {
+        let base = match self.clock {
+            Clock::Host => SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs() as i64),
+            Clock::Fixed(t) => t + (now / FREQUENCY) as i64,
+        };
+
+        base + self.offset
+    }
+
+    /// Pins driven by the RTC
+    pub fn read(&self) -> u8 {
+        self.output << 1
+    }
+
+    /// Pins driven by the GBA, data is latched on rising edges of SCK
+    pub fn write(&mut self, pins: u8, now: u64) {
+        let rising = self.pins & SCK == 0 && pins & SCK != 0;
+        self.pins = pins;
+
+        if pins & CS == 0 {
+            self.state = State::Idle;
+            return;
+        }
+
+        if self.state == State::Idle {
+            self.state = State::Command;
+            self.value = 0;
+            self.bits = 0;
+        }
+
+        if !rising {
+            return;
+        }
+
+        let sio = (pins & SIO) >> 1;
+        match self.state {
+            // Command bytes are sent MSB first, 0110 is a fixed code
+            State::Command => {
+                self.value = self.value << 1 | sio;
+                self.bits += 1;
+                if self.bits == 8 {
+                    self.command(now);
+                }
+            }
+            // Parameters are sent LSB first
+            State::Read(command) => {
+                self.output = self.buffer[self.index] >> self.bits & 1;
+                self.next_bit(command, now);
+            }
+            State::Write(command) => {
+                self.value |= sio << self.bits;
+                self.next_bit(command, now);
+            }
+            State::Idle | State::Done => (),
+        }
+    }
+
+    fn command(&mut self, now: u64) {
+        let command = (self.value >> 1 & 7) as usize;
+        let read = self.value & 1 != 0;
+        let valid = self.value >> 4 == 0b0110;
+
+        self.bits = 0;
+        self.index = 0;
+        self.value = 0;
+
+        self.state = match (valid, Self::length(command), read) {
+            (false, _, _) => State::Done,
+            (true, 0, _) => {
+                // Reset, time goes back to 2000-01-01
+                if command == 0 {
+                    self.status = 0;
+                    self.offset += Y2K - self.time(now);
+                }
+                State::Done
+            }
+            (true, _, true) => {
+                self.fill(command, now);
+                State::Read(command)
+            }
+            (true, _, false) => State::Write(command),
+        };
+    }
+
+    /// Advance to the next bit of parameter transfer
+    fn next_bit(&mut self, command: usize, now: u64) {
+        self.bits += 1;
+        if self.bits < 8 {
+            return;
+        }
+
+        if let State::Write(_) = self.state {
+            self.buffer[self.index] = self.value;
+            self.value = 0;
+        }
+
+        self.bits = 0;
+        self.index += 1;
+        if self.index == Self::length(command) {
+            if let State::Write(_) = self.state {
+                self.apply(command, now);
+            }
+            self.state = State::Done;
+        }
+    }
+
+    /// Number of parameter bytes of each command
+    fn length(command: usize) -> usize {
+        match command {
+            1 => 1, // Status
+            2 => 7, // Date and time
+            3 => 3, // Time
+            4 => 2, // Alarm / interrupt frequency, ignored
+            _ => 0,
+        }
+    }
+
+    /// Prepare registers to be read
+    fn fill(&mut self, command: usize, now: u64) {
+        let time = self.time(now);
+        let (year, month, day) = civil_from_days(time.div_euclid(86400));
+        let secs = time.rem_euclid(86400);
+        let (hour, minute, second) = (secs / 3600, secs / 60 % 60, secs % 60);
+        let weekday = (time.div_euclid(86400) + 4).rem_euclid(7);
+
+        // Bit 7 of hour is the PM flag in 12 hour mode
+        let hour = if self.status & 0x40 != 0 {
+            bcd(hour)
+        } else {
+            bcd(hour % 12) | ((hour >= 12) as u8) << 7
+        };
+
+        match command {
+            1 => self.buffer[0] = self.status,
+            2 => {
+                self.buffer = [
+                    bcd(year % 100),
+                    bcd(month),
+                    bcd(day),
+                    bcd(weekday),
+                    hour,
+                    bcd(minute),
+                    bcd(second),
+                ]
+            }
+            3 => self.buffer[..3].copy_from_slice(&[hour, bcd(minute), bcd(second)]),
+            _ => self.buffer = [0; 7],
+        }
+    }
+
+    /// Write received registers
+    fn apply(&mut self, command: usize, now: u64) {
+        let time = self.time(now);
+        let days = time.div_euclid(86400);
+
+        let b = self.buffer;
+        let hour = |h: u8| {
+            let pm = self.status & 0x40 == 0 && h & 0x80 != 0;
+            binary(h & 0x3f) % 24 + if pm { 12 } else { 0 }
+        };
+
+        let target = match command {
+            1 => {
+                self.status = b[0] & 0x6a;
+                return;
+            }
+            2 => {
+                let days = days_from_civil(2000 + binary(b[0]), binary(b[1]), binary(b[2]));
+                days * 86400 + hour(b[4]) * 3600 + binary(b[5]) * 60 + binary(b[6])
+            }
+            3 => days * 86400 + hour(b[0]) * 3600 + binary(b[1]) * 60 + binary(b[2]),
+            _ => return,
+        };
+
+        self.offset += target - time;
+    }
+}
+
+fn bcd(value: i64) -> u8 {
+    (value / 10 * 16 + value % 10) as u8
+}
+
+fn binary(value: u8) -> i64 {
+    (value >> 4) as i64 * 10 + (value & 0xf) as i64
+}
+
+/// Convert days since Unix epoch to (year, month, day)
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+
+    (year, month, day)
+}
+
+/// Convert a date to days since Unix epoch
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+    era * 146097 + doe - 719468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn send_command(rtc: &mut Rtc, command: u8) {
+        rtc.write(0, 0);
+        rtc.write(CS, 0);
+        for i in (0..8).rev() {
+            let sio = (command >> i & 1) << 1;
+            rtc.write(CS | sio, 0);
+            rtc.write(CS | SCK | sio, 0);
+        }
+    }
+
+    fn read_byte(rtc: &mut Rtc) -> u8 {
+        (0..8).fold(0, |value, i| {
+            rtc.write(CS, 0);
+            rtc.write(CS | SCK, 0);
+            value | (rtc.read() >> 1) << i
+        })
+    }
+
+    #[test]
+    fn read_datetime() {
+        // 2004-09-17 13:45:30, a Friday
+        let mut rtc = Rtc::new(Clock::Fixed(1095428730));
+        send_command(&mut rtc, 0x65);
+
+        let datetime: Vec<u8> = (0..7).map(|_| read_byte(&mut rtc)).collect();
+        assert_eq!(datetime, [0x04, 0x09, 0x17, 0x05, 0x13, 0x45, 0x30]);
+    }
+
+    #[test]
+    fn calendar() {
+        for days in [-1000, 0, 11016, 12477, 20000] {
+            let (y, m, d) = civil_from_days(days);
+            assert_eq!(days_from_civil(y, m, d), days);
+        }
+        assert_eq!(civil_from_days(11016), (2000, 2, 29));
+    }
+}
@@ -0,0 +1,186 @@
+//! Sensors on game paks, the solar and gyro sensors sit behind the GPIO
+//! port while the tilt sensor is mapped to the SRAM region
+
+/// Boktai solar sensor, a counter compared against the light level.
+/// Pin 0 clocks the counter, pin 1 resets it and pin 3 outputs the result.
+pub struct Solar {
+    pub level: u8, // Light level given by the frontend, 0 - dark, 255 - bright
+    counter: u8,   // Clock edges since last reset
+    threshold: u8, // Counter value at which the output goes high
+    clock: bool,   // Previous state of the clock pin
+}
+
+util::snapshot!(Solar {
+    level,
+    counter,
+    threshold,
+    clock
+});
+
+impl Solar {
+    pub fn new() -> Self {
+        Self {
+            level: 0,
+            counter: 0,
+            threshold: 0xff,
+            clock: false,
+        }
+    }
+
+    pub fn read(&self) -> u8 {
+        ((self.counter >= self.threshold) as u8) << 3
+    }
+
+    pub fn write(&mut self, pins: u8) {
+        // Brighter light charges the ADC faster
+        if pins & 2 != 0 {
+            self.counter = 0;
+            self.threshold = 0xff - self.level;
+        }
+
+        let clock = pins & 1 != 0;
+        if clock && !self.clock {
+            self.counter = self.counter.saturating_add(1);
+        }
+        self.clock = clock;
+    }
+}
+
+/// WarioWare Twisted gyro sensor, pin 0 starts a conversion and the 16 bit
+/// sample is shifted out MSB first to pin 2 on falling edges of pin 1
+pub struct Gyro {
+    pub rotation: i16, // Angular velocity given by the frontend
+    sample: u16,       // Bits left to be shifted out
+    output: u8,        // Value of the data pin
+    clock: bool,       // Previous state of the clock pin
+}
+
+util::snapshot!(Gyro {
+    rotation,
+    sample,
+    output,
+    clock
+});
+
+impl Gyro {
+    pub fn new() -> Self {
+        Self {
+            rotation: 0,
+            sample: 0,
+            output: 0,
+            clock: false,
+        }
+    }
+
+    pub fn read(&self) -> u8 {
+        self.output << 2
+    }
+
+    pub fn write(&mut self, pins: u8) {
+        // 0x6c0 is the sensor value at rest
+        if pins & 1 != 0 {
+            self.sample = (0x6c0 + (self.rotation >> 5)) as u16;
+        }
+
+        let clock = pins & 2 != 0;
+        if self.clock && !clock {
+            self.output = (self.sample >> 15) as u8;
+            self.sample <<= 1;
+        }
+        self.clock = clock;
+    }
+}
+
+/// Yoshi Topsy-Turvy tilt sensor. Writing 0x55 to 0x0e008000 and then 0xaa
+/// to 0x0e008100 latches 12 bit X / Y values, read at 0x0e008200 - 0x0e008500.
+pub struct Tilt {
+    pub x: i16,        // Tilt to the right given by the frontend
+    pub y: i16,        // Tilt towards the player given by the frontend
+    latch: bool,       // 0x55 has been written
+    value: (u16, u16), // Latched X / Y values
+}
+
+util::snapshot!(Tilt { x, y, latch, value });
+
+impl Tilt {
+    /// Yoshi Topsy-Turvy and Koro Koro Puzzle, by game code
+    pub fn detect(rom: &[u8]) -> Option<Self> {
+        let code = rom.get(0xac..0xaf)?;
+        (code == b"KYG" || code == b"KHP").then(Self::new)
+    }
+
+    pub fn new() -> Self {
+        Self {
+            x: 0,
+            y: 0,
+            latch: false,
+            value: (0x3a0, 0x3a0),
+        }
+    }
+
+    /// Only the upper half of the SRAM region is used
+    #[inline]
+    pub fn mapped(address: usize) -> bool {
+        (0x8000..0x8600).contains(&address)
+    }
+
+    pub fn load8(&self, address: usize) -> u8 {
+        let (x, y) = self.value;
+        match address {
+            0x8200 => x as u8,
+            // Bit 7 tells a sample is ready
+            0x8300 => (x >> 8) as u8 & 0xf | 0x80,
+            0x8400 => y as u8,
+            0x8500 => (y >> 8) as u8 & 0xf,
+            _ => 0,
+        }
+    }
+
+    pub fn store8(&mut self, address: usize, value: u8) {
+        match (address, value) {
+            (0x8000, 0x55) => self.latch = true,
+            (0x8100, 0xaa) if self.latch => {
+                // 0x3a0 is the sensor value when level
+                let x = 0x3a0 - (self.x >> 5);
+                let y = 0x3a0 - (self.y >> 5);
+                self.value = (x as u16, y as u16);
+                self.latch = false;
+            }
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn solar_brighter_counts_less() {
+        let count = |level| {
+            let mut solar = Solar::new();
+            solar.level = level;
+            solar.write(2);
+            solar.write(0);
+            (0..=255).find(|_| {
+                solar.write(1);
+                solar.write(0);
+                solar.read() != 0
+            })
+        };
+
+        assert!(count(0xe0) < count(0x20));
+    }
+
+    #[test]
+    fn tilt_latch() {
+        let mut tilt = Tilt::new();
+        tilt.x = -0x1000;
+        tilt.store8(0x8100, 0xaa);
+        assert_eq!(tilt.load8(0x8200), 0xa0);
+
+        tilt.store8(0x8000, 0x55);
+        tilt.store8(0x8100, 0xaa);
+        assert_eq!((tilt.load8(0x8200), tilt.load8(0x8300)), (0x20, 0x84));
+    }
+}
@@ -1,7 +1,8 @@
 use crate::bus::GbaBus;
+use crate::cart::Backup;
 use crate::interrupt::Irq::*;
 use crate::interrupt::IrqController;
-use util::Bus;
+use util::{snapshot, Access, Bus, Snapshot};
 
 pub struct Dma {
     pub channel: Vec<DmaChannel>,
@@ -20,6 +21,7 @@ pub struct DmaChannel {
     pub srcinc: u32,   // Added to in_src after every copy
     pub dstinc: u32,   // Added to dst_src after every copy
     pub in_count: u16, // Keep track of how many words transferred
+    pub length: u16,   // Number of units of current transfer
 
     transfer: fn(&mut Self, &mut GbaBus),
     pub state: DMAState,
@@ -28,6 +30,37 @@ pub struct DmaChannel {
     pub active: bool,
 }
 
+// `transfer` is picked again from the control bits on load
+snapshot!(DmaChannel {
+    src,
+    dst,
+    count,
+    control,
+    in_src,
+    in_dst,
+    srcinc,
+    dstinc,
+    in_count,
+    length,
+    state,
+    cycles,
+    active
+});
+
+impl Snapshot for Dma {
+    fn save_state(&self, out: &mut Vec<u8>) {
+        DmaChannel::save_slice(&self.channel, out);
+    }
+
+    fn load_state(&mut self, input: &mut &[u8]) -> std::io::Result<()> {
+        DmaChannel::load_slice(&mut self.channel, input)?;
+        self.channel
+            .iter_mut()
+            .for_each(DmaChannel::select_transfer);
+        Ok(())
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum DMAState {
     Unintialized,
@@ -35,6 +68,22 @@ pub enum DMAState {
     Finished,
 }
 
+impl Snapshot for DMAState {
+    fn save_state(&self, out: &mut Vec<u8>) {
+        (self.clone() as u8).save_state(out);
+    }
+
+    fn load_state(&mut self, input: &mut &[u8]) -> std::io::Result<()> {
+        *self = match snapshot::take(input, 1)?[0] {
+            0 => DMAState::Unintialized,
+            1 => DMAState::Transferring,
+            2 => DMAState::Finished,
+            _ => return Err(snapshot::invalid("DMA state")),
+        };
+        Ok(())
+    }
+}
+
 impl Dma {
     pub fn new() -> Self {
         let mut d = Self {
@@ -61,7 +110,7 @@ impl Dma {
 
     pub fn request_hblank(&mut self) {
         for c in self.channel.iter_mut() {
-            if c.start() == 0b10 {
+            if c.enable() && c.start() == 0b10 {
                 c.active = true;
             }
         }
@@ -69,7 +118,18 @@ impl Dma {
 
     pub fn request_vblank(&mut self) {
         for c in self.channel.iter_mut() {
-            if c.start() == 0b01 {
+            if c.enable() && c.start() == 0b01 {
+                c.active = true;
+            }
+        }
+    }
+
+    /// Sound FIFO A / B request a refill from DMA 1 or 2 in special start mode
+    pub fn request_fifo(&mut self, fifo: usize) {
+        let address = [0x040000a0, 0x040000a4][fifo];
+
+        for c in self.channel[1..=2].iter_mut() {
+            if c.enable() && c.start() == 0b11 && c.dst == address {
                 c.active = true;
             }
         }
@@ -97,6 +157,7 @@ impl DmaChannel {
             control: 0,
 
             in_count: 0,
+            length: 0,
             in_src: 0,
             in_dst: 0,
             srcinc: 0,
@@ -112,14 +173,12 @@ impl DmaChannel {
 
     /// Things to be done before transfer initiates,
     /// e.g. Copy into internal register, calculate increment...
-    pub fn setup(&mut self) {
+    pub fn setup(&mut self, bus: &mut GbaBus) {
         if !self.active {
             dbg!(self.index);
             return;
         }
 
-        // Start mode 3 not handled
-        assert_ne!(self.start(), 3);
         assert!(self.active);
         assert!(self.enable());
 
@@ -129,14 +188,35 @@ impl DmaChannel {
 
         self.srcinc = self.get_increment(self.srccnt());
         self.dstinc = self.get_increment(self.dstcnt());
+        self.length = self.count;
+
+        self.select_transfer();
+
+        // EEPROM address width is only known from the length of requests
+        if self.in_dst >> 24 == 0x0d {
+            if let Backup::Eeprom(e) = &mut bus.cart.backup {
+                e.set_transfer_length(self.length);
+            }
+        }
 
-        self.transfer = if self.word_f() {
+        // Sound FIFO mode always transfers 4 words to a fixed address,
+        // the word bit is ignored
+        if self.sound_f() {
+            self.length = 4;
+            self.srcinc = Self::increment(self.srccnt(), 4);
+            self.dstinc = 0;
+        }
+
+        self.state = DMAState::Transferring;
+    }
+
+    /// Pick transfer width from control bits, sound FIFO mode is always 32 bit
+    pub fn select_transfer(&mut self) {
+        self.transfer = if self.word_f() || self.sound_f() {
             Self::transfer32
         } else {
             Self::transfer16
         };
-
-        self.state = DMAState::Transferring;
     }
 
     /// Things to be done after transfer completes,
@@ -171,7 +251,7 @@ impl DmaChannel {
         self.cycles = 0;
 
         match self.state {
-            Unintialized => self.setup(),
+            Unintialized => self.setup(bus),
             Transferring => (self.transfer)(self, bus),
             Finished => self.finish(irqcnt),
         }
@@ -180,41 +260,63 @@ impl DmaChannel {
     }
 
     pub fn transfer16(&mut self, bus: &mut GbaBus) {
-        if self.in_count < self.count {
+        if self.in_count < self.length {
             bus.store16(self.in_dst as usize, bus.load16(self.in_src as usize));
 
+            // Only the first transfer is non sequential
+            let access = if self.in_count == 0 {
+                Access::NonSeq
+            } else {
+                Access::Seq
+            };
+            self.cycles += bus.access_cycles(self.in_src as usize, 1, access);
+            self.cycles += bus.access_cycles(self.in_dst as usize, 1, access);
+
             // Incrment internal register
             self.in_src = self.in_src.wrapping_add(self.srcinc);
             self.in_dst = self.in_dst.wrapping_add(self.dstinc);
 
             self.in_count += 1;
-
-            self.cycles += GbaBus::access_timing(self.in_dst as usize, 1);
-            self.cycles += GbaBus::access_timing(self.in_src as usize, 1);
         } else {
             self.state = DMAState::Finished;
         }
     }
 
     pub fn transfer32(&mut self, bus: &mut GbaBus) {
-        if self.in_count < self.count {
+        if self.in_count < self.length {
             bus.store32(self.in_dst as usize, bus.load32(self.in_src as usize));
 
+            // Only the first transfer is non sequential
+            let access = if self.in_count == 0 {
+                Access::NonSeq
+            } else {
+                Access::Seq
+            };
+            self.cycles += bus.access_cycles(self.in_src as usize, 2, access);
+            self.cycles += bus.access_cycles(self.in_dst as usize, 2, access);
+
             // Incrment internal register
             self.in_src = self.in_src.wrapping_add(self.srcinc);
             self.in_dst = self.in_dst.wrapping_add(self.dstinc);
 
             self.in_count += 1;
-
-            self.cycles += GbaBus::access_timing(self.in_dst as usize, 2);
-            self.cycles += GbaBus::access_timing(self.in_src as usize, 2);
         } else {
             self.state = DMAState::Finished;
         }
     }
 
+    /// DMA 1 and 2 in special start mode feed the sound FIFOs
+    #[inline]
+    pub fn sound_f(&self) -> bool {
+        (self.index == 1 || self.index == 2) && self.start() == 0b11
+    }
+
     pub fn get_increment(&self, cnt: u32) -> u32 {
-        let inc: u32 = if self.word_f() { 4 } else { 2 };
+        Self::increment(cnt, if self.word_f() { 4 } else { 2 })
+    }
+
+    /// Step of an address control setting for units of `inc` bytes
+    fn increment(cnt: u32, inc: u32) -> u32 {
         match cnt {
             0b00 => inc,
             0b01 => inc.wrapping_neg(),
@@ -224,3 +326,30 @@ impl DmaChannel {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::Gba;
+
+    #[test]
+    fn sound_fifo_word_bit_ignored() {
+        let mut gba = Box::new(Gba::new());
+        gba.init();
+
+        // Special start, repeat, incrementing source, word bit clear
+        let c = &mut gba.dma.channel[1];
+        c.src = 0x02000000;
+        c.dst = 0x02001000;
+        c.control = 0xb200;
+
+        for refill in 1..=2 {
+            c.active = true;
+            c.setup(&mut gba.bus);
+            for _ in 0..4 {
+                c.transfer32(&mut gba.bus);
+            }
+            assert_eq!(c.in_src, 0x02000000 + 16 * refill);
+            c.finish(&mut gba.irqcnt);
+        }
+    }
+}
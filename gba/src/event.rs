@@ -1,87 +1,182 @@
-//! Prototypical event scheduler
-// use std::collections::VecDeque;
-use crate::Console;
-
-// A Rough picture for how events are scheduled
-
-// PPU runs for 960 ticks
-// /------ HDraw ------/
-// DMA, CPU, and Timers run for another 960 ticks
-// /------- 960 -------/
-// PPU idles for 272 ticks
-// / HBlank /
-// DMA, CPU, and Timers consumes 272 ticks with decreasing priority
-// /-- 272 -/
-// /.................../
-// /......../
-// Repeat until VCount = 160
-
-// DMA, CPU, Timers still work similarly
-// /------ VBlank -----/
-// /------- 960 -------/
-// / HBlank /
-// /-- 272 -/
-
-enum Event {
+//! Event scheduler
+//! Components that act at a known point in time (PPU line phases,
+//! timer overflows, DMA starts, APU samples) schedule an event instead
+//! of being ticked after every instruction. The CPU and DMA then run
+//! uninterrupted until the earliest event is due.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use util::{snapshot, Snapshot};
+
+// A rough picture of how a scanline is scheduled
+
+// /------ HDraw ------/ HBlank /
+// /------- 960 -------/- 272 -/
+// VCount is incremented at the end of each line, after 160 visible
+// lines VBlank starts, and a frame ends once VCount reaches 228.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Event {
+    HDraw,
+    HBlank,
+    VCount,
+    Timer(usize), // Timer 0 - 3 overflow
+    Dma(usize),   // Immediate start of DMA 0 - 3
+    Apu,          // Output a sample
+}
+
+impl Snapshot for Event {
+    fn save_state(&self, out: &mut Vec<u8>) {
+        let (tag, index) = match *self {
+            Event::HDraw => (0u8, 0),
+            Event::HBlank => (1, 0),
+            Event::VCount => (2, 0),
+            Event::Timer(i) => (3, i),
+            Event::Dma(i) => (4, i),
+            Event::Apu => (5, 0),
+        };
+        out.extend_from_slice(&[tag, index as u8]);
+    }
+
+    fn load_state(&mut self, input: &mut &[u8]) -> std::io::Result<()> {
+        let bytes = snapshot::take(input, 2)?;
+        let index = bytes[1] as usize;
+        *self = match (bytes[0], index) {
+            (0, _) => Event::HDraw,
+            (1, _) => Event::HBlank,
+            (2, _) => Event::VCount,
+            (3, 0..=3) => Event::Timer(index),
+            (4, 0..=3) => Event::Dma(index),
+            (5, _) => Event::Apu,
+            _ => return Err(snapshot::invalid("event")),
+        };
+        Ok(())
+    }
+}
+
+pub struct Scheduler {
+    pub now: u64, // Cycles elapsed since power on
+    queue: BinaryHeap<Reverse<(u64, Event)>>,
 }
 
-struct Scheduler {
-    queue: std::collections::VecDeque<(Event, u64)>,
+impl Snapshot for Scheduler {
+    fn save_state(&self, out: &mut Vec<u8>) {
+        self.now.save_state(out);
+        (self.queue.len() as u32).save_state(out);
+
+        // Heap order depends on insertion history, sort for identical bytes
+        let events = self.queue.clone().into_sorted_vec();
+        events.iter().for_each(|Reverse(e)| e.save_state(out));
+    }
+
+    fn load_state(&mut self, input: &mut &[u8]) -> std::io::Result<()> {
+        self.now.load_state(input)?;
+        let mut len = 0u32;
+        len.load_state(input)?;
+
+        self.queue.clear();
+        for _ in 0..len {
+            let mut event = (0, Event::HDraw);
+            event.load_state(input)?;
+            self.queue.push(Reverse(event));
+        }
+        Ok(())
+    }
 }
 
-impl Console {
-    pub fn schedule(&mut self) {
-        use super::ppu::PPUState::*;
-
-        let cpu = &mut self.cpu;
-        let ppu = &mut self.ppu;
-        let memory = &mut self.memory;
-        let timers = &mut self.timers;
-        let dma = &mut self.dma;
-        let irqcnt = &mut self.irqcnt;
-
-        while ppu.vcount < 226 {
-            let ppu_ticks = match ppu.state {
-                HDraw => {
-                    ppu.hdraw();
-                    960
-                }
-                HBlankStart => {
-                    ppu.hblank(irqcnt);
-                    dma.request_hblank();
-                    0
-                }
-                HBlank => {
-                    ppu.state = EndOfLine;
-                    272
-                }
-                VBlankStart => {
-                    ppu.vblank(irqcnt);
-                    dma.request_vblank();
-                    0
-                }
-                VBlank => {
-                    ppu.state = HBlank;
-                    960
-                }
-                EndOfLine => {
-                    ppu.increment_vcount(irqcnt);
-                    0
-                }
-            };
-
-            let mut remaining_ticks = ppu_ticks;
-            while remaining_ticks > 0 {
-                remaining_ticks -= if dma.is_active() {
-                    dma.step(irqcnt, memory)
-                } else {
-                    cpu.step(memory)
-                }
-            }
-
-            timers.run(ppu_ticks, irqcnt);
+impl Scheduler {
+    pub fn new() -> Self {
+        Self {
+            now: 0,
+            queue: BinaryHeap::new(),
         }
+    }
+
+    /// Schedule `event` to happen `delay` cycles from now
+    #[inline]
+    pub fn schedule(&mut self, event: Event, delay: u64) {
+        self.schedule_at(event, self.now + delay);
+    }
+
+    #[inline]
+    pub fn schedule_at(&mut self, event: Event, time: u64) {
+        self.queue.push(Reverse((time, event)));
+    }
+
+    /// Remove all pending occurrences of `event`
+    pub fn cancel(&mut self, event: Event) {
+        self.queue.retain(|Reverse((_, e))| *e != event);
+    }
+
+    /// Return true if the earliest event is due
+    #[inline]
+    pub fn pending(&self) -> bool {
+        match self.queue.peek() {
+            Some(Reverse((time, _))) => *time <= self.now,
+            None => false,
+        }
+    }
+
+    /// Timestamp of the earliest event
+    #[inline]
+    pub fn next(&self) -> Option<u64> {
+        self.queue.peek().map(|Reverse((time, _))| *time)
+    }
+
+    /// Remove the earliest event if it is due, along with its timestamp
+    #[inline]
+    pub fn pop(&mut self) -> Option<(u64, Event)> {
+        if self.pending() {
+            self.queue.pop().map(|Reverse(e)| e)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn earliest_first() {
+        let mut scheduler = Scheduler::new();
+
+        scheduler.schedule(Event::HBlank, 960);
+        scheduler.schedule(Event::Timer(0), 10);
+        scheduler.schedule(Event::Apu, 512);
+        assert!(!scheduler.pending());
+
+        scheduler.now = 1000;
+        assert_eq!(scheduler.pop(), Some((10, Event::Timer(0))));
+        assert_eq!(scheduler.pop(), Some((512, Event::Apu)));
+        assert_eq!(scheduler.pop(), Some((960, Event::HBlank)));
+        assert_eq!(scheduler.pop(), None);
+    }
+
+    #[test]
+    fn cancel() {
+        let mut scheduler = Scheduler::new();
+
+        scheduler.schedule(Event::Timer(1), 0);
+        scheduler.schedule(Event::Timer(2), 0);
+        scheduler.cancel(Event::Timer(1));
+        assert_eq!(scheduler.pop(), Some((0, Event::Timer(2))));
+        assert!(!scheduler.pending());
+    }
+
+    #[test]
+    fn snapshot_order() {
+        let mut a = Scheduler::new();
+        let mut b = Scheduler::new();
+        let events = [(Event::Apu, 512), (Event::HBlank, 960), (Event::Dma(3), 4)];
+
+        events.iter().for_each(|&(e, t)| a.schedule(e, t));
+        events.iter().rev().for_each(|&(e, t)| b.schedule(e, t));
 
-        ppu.increment_vcount(irqcnt);
+        let (mut x, mut y) = (Vec::new(), Vec::new());
+        a.save_state(&mut x);
+        b.save_state(&mut y);
+        assert_eq!(x, y);
     }
 }
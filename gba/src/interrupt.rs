@@ -19,19 +19,55 @@ pub enum Irq {
     GamePak = 1 << 13,
 }
 
+/// Low power mode entered by writing HALTCNT
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Power {
+    Normal,
+    Halt, // CPU paused until any enabled interrupt is requested
+    Stop, // CPU paused until a keypad, serial or cartridge interrupt is requested
+}
+
+impl Snapshot for Power {
+    fn save_state(&self, out: &mut Vec<u8>) {
+        (*self as u8).save_state(out);
+    }
+
+    fn load_state(&mut self, input: &mut &[u8]) -> std::io::Result<()> {
+        *self = match snapshot::take(input, 1)?[0] {
+            0 => Power::Normal,
+            1 => Power::Halt,
+            2 => Power::Stop,
+            _ => return Err(snapshot::invalid("power mode")),
+        };
+        Ok(())
+    }
+}
+
 #[derive(Debug)]
 pub struct IrqController {
-    pub ime: u16, // Interrupt master enable flag
-    pub ie: u16,  // Interrupt enable flag
-    pub irf: u16, // Interrupt request flag
+    pub ime: u16,     // Interrupt master enable flag
+    pub ie: u16,      // Interrupt enable flag
+    pub irf: u16,     // Interrupt request flag
+    pub postflg: u8,  // Set by BIOS after the first boot
+    pub power: Power, // Halted / stopped state
 }
 
+util::snapshot!(IrqController {
+    ime,
+    ie,
+    irf,
+    postflg,
+    power
+});
+
 impl IrqController {
     pub fn new() -> Self {
         Self {
             ime: 0,
             ie: 0,
             irf: 0,
+            postflg: 0,
+            power: Power::Normal,
         }
     }
 
@@ -43,6 +79,25 @@ impl IrqController {
         self.irf |= irq as u16;
     }
 
+    /// Return true if the CPU is halted or stopped, waking it up if an
+    /// enabled interrupt is requested. IME does not affect waking up.
+    pub fn sleeping(&mut self) -> bool {
+        let wake = match self.power {
+            Power::Normal => return false,
+            Power::Halt => self.ie & self.irf != 0,
+            Power::Stop => {
+                let mask = Irq::Keypad as u16 | Irq::Serial as u16 | Irq::GamePak as u16;
+                self.ie & self.irf & mask != 0
+            }
+        };
+
+        if wake {
+            self.power = Power::Normal;
+        }
+
+        !wake
+    }
+
     pub fn check(&mut self, cpu: &mut cpu::Cpu) {
         if self.pending() {
             util::info!("Hardware interrupt triggered by irqcnt");
@@ -51,3 +106,79 @@ impl IrqController {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use util::Bus;
+
+    #[test]
+    fn halt_wakes_without_ime() {
+        let mut irqcnt = IrqController::new();
+        irqcnt.ie = Irq::VBlank as u16;
+        irqcnt.power = Power::Halt;
+        assert!(irqcnt.sleeping());
+
+        irqcnt.request(Irq::VBlank);
+        assert!(!irqcnt.sleeping());
+        assert_eq!(irqcnt.power, Power::Normal);
+    }
+
+    #[test]
+    fn stop_ignores_vblank() {
+        let mut irqcnt = IrqController::new();
+        irqcnt.ie = Irq::VBlank as u16 | Irq::Keypad as u16;
+        irqcnt.power = Power::Stop;
+
+        irqcnt.request(Irq::VBlank);
+        assert!(irqcnt.sleeping());
+
+        irqcnt.request(Irq::Keypad);
+        assert!(!irqcnt.sleeping());
+    }
+
+    #[test]
+    fn stop_pauses_clocks() {
+        let mut gba = Box::new(crate::Gba::new());
+        gba.init();
+
+        // Enable VBlank and keypad IRQ, stop, then count wake ups
+        let code = [
+            0xe3a00301u32, // mov r0, #0x04000000
+            0xe3a02008,    // mov r2, #8
+            0xe1c020b4,    // strh r2, [r0, #4]
+            0xe2801c02,    // add r1, r0, #0x200
+            0xe3a02a01,    // mov r2, #0x1000
+            0xe3822001,    // orr r2, r2, #1
+            0xe1c120b0,    // strh r2, [r1]
+            0xe3a02080,    // mov r2, #0x80
+            0xe2801c03,    // add r1, r0, #0x300
+            0xe5c12001,    // strb r2, [r1, #1]
+            0xe3a03403,    // mov r3, #0x03000000
+            0xe3a02001,    // mov r2, #1
+            0xe5832000,    // str r2, [r3]
+            0xeafffffe,    // b .
+        ];
+        let mut rom: Vec<u8> = code.iter().flat_map(|c| c.to_le_bytes()).collect();
+        rom.resize(0x200, 0);
+        gba.load_rom(rom, None);
+        gba.skip_bios();
+
+        gba.step_frame();
+        assert_eq!(gba.irqcnt.power, Power::Stop);
+        let (now, vcount) = (gba.scheduler.now, gba.ppu.vcount);
+
+        // Neither the PPU nor VBlank moves on
+        for _ in 0..3 {
+            gba.step_frame();
+        }
+        assert_eq!(gba.scheduler.now, now);
+        assert_eq!(gba.ppu.vcount, vcount);
+        assert_eq!(gba.bus.load32(0x03000000), 0);
+
+        gba.irqcnt.request(Irq::Keypad);
+        gba.step_frame();
+        assert_eq!(gba.irqcnt.power, Power::Normal);
+        assert_eq!(gba.bus.load32(0x03000000), 1);
+    }
+}
@@ -2,11 +2,18 @@ use crate::interrupt::Irq::*;
 use crate::interrupt::IrqController;
 use util::*;
 
+/// Button names in KEYINPUT bit order
+pub static BUTTONS: [&str; 10] = [
+    "a", "b", "select", "start", "right", "left", "up", "down", "r", "l",
+];
+
 pub struct Keypad {
     pub keyinput: u16,
     pub keycnt: u16,
 }
 
+util::snapshot!(Keypad { keyinput, keycnt });
+
 impl Keypad {
     pub fn new() -> Self {
         Self {
@@ -1,49 +1,85 @@
 #![allow(clippy::new_without_default)]
 
+mod apu;
+mod audio;
+mod bios;
+mod bus;
 mod cart;
 mod dma;
-// mod event;
-mod bus;
+mod event;
 mod interrupt;
 mod keypad;
+mod movie;
+mod rewind;
+mod state;
 mod timer;
 
+use apu::Apu;
 use bus::GbaBus;
 use cart::Cart;
 use dma::Dma;
-use interrupt::IrqController;
+use event::{Event, Scheduler};
+use interrupt::{IrqController, Power};
 use keypad::Keypad;
 use ppu::Ppu;
+use rewind::Rewind;
 use timer::Timers;
 
+pub use audio::{AudioSink, Resampler, WavWriter};
+pub use cart::{BackupType, Chip, Clock};
 pub use cpu::Cpu;
+pub use keypad::BUTTONS;
+pub use movie::{Input, Movie};
+pub use state::STATE_VERSION;
 
 pub struct Gba {
     pub cpu: Cpu,
     pub ppu: Ppu,
+    pub apu: Apu,
     pub dma: Dma,
     pub bus: GbaBus,
     pub timers: Timers,
     pub irqcnt: IrqController,
     pub keypad: Keypad,
     pub cart: Cart,
+    pub scheduler: Scheduler,
+    pub frame: u64, // Frames run since power on
 
     pub callback: Option<fn()>,
+    pub sink: Option<Box<dyn AudioSink>>,
+    pub rumble: Option<fn(bool)>, // Called when the rumble motor turns on / off
+    rumbling: bool,
+    history: Option<Rewind>, // States kept for rewinding
+    replaying: bool,         // Catching up after rewinding, no output
+    movie: Option<Movie>,    // Input being recorded or played back
 }
 
 impl Gba {
     pub fn new() -> Gba {
+        let mut scheduler = Scheduler::new();
+        scheduler.schedule(Event::HDraw, 0);
+        scheduler.schedule(Event::Apu, 512);
+
         Self {
             cpu: Cpu::new(),
             ppu: Ppu::new(),
+            apu: Apu::new(),
             dma: Dma::new(),
             irqcnt: IrqController::new(),
             timers: Timers::new(),
             keypad: Keypad::new(),
             bus: GbaBus::new(),
             cart: Cart::with_rom(Vec::new()),
+            scheduler,
+            frame: 0,
 
             callback: None,
+            sink: None,
+            rumble: None,
+            rumbling: false,
+            history: None,
+            replaying: false,
+            movie: None,
         }
     }
 
@@ -53,77 +89,240 @@ impl Gba {
 
     /// Render a frame
     pub fn step_frame(&mut self) {
-        use interrupt::Irq::*;
-
         if let Some(f) = self.callback {
             f();
         }
 
+        // Only samples of the current frame are kept
+        self.apu.samples.clear();
+        self.movie_input();
+
+        // A stopped console only wakes up on input between frames
+        while self.step_dma_cpu() {
+            let (time, event) = self.scheduler.pop().unwrap();
+            if self.handle_event(event, time) {
+                break;
+            }
+        }
+
+        // Frames run again by rewinding were already output
+        if !self.replaying {
+            self.output_frame();
+        }
+
+        self.movie_checkpoint();
+        self.frame += 1;
+        if let Some(mut history) = self.history.take() {
+            if history.due(self.frame) {
+                history.push(self.frame, self.save_state());
+            }
+            self.history = Some(history);
+        }
+    }
+
+    /// Pass sound, rumble and save data of a frame to the host
+    fn output_frame(&mut self) {
+        if let Some(sink) = &mut self.sink {
+            sink.write(&self.apu.samples, self.apu.sample_rate());
+        }
+
+        let rumbling = self.cart.rumble();
+        if let (Some(f), true) = (self.rumble, rumbling != self.rumbling) {
+            f(rumbling);
+        }
+        self.rumbling = rumbling;
+
+        if let Err(e) = self.cart.update_save() {
+            util::warn!("Failed to write save file: {}", e);
+        }
+    }
+
+    /// CRC32 of the frame buffer, as little endian RGB555 pixels
+    pub fn frame_hash(&self) -> u32 {
+        let bytes: Vec<u8> = self
+            .ppu
+            .buffer
+            .iter()
+            .flat_map(|p| p.to_le_bytes())
+            .collect();
+        util::crc32(&bytes)
+    }
+
+    /// Keep a state every `interval` frames for rewinding,
+    /// using at most about `capacity` bytes for older states
+    pub fn enable_rewind(&mut self, interval: u32, capacity: usize) {
+        self.history = Some(Rewind::new(interval, capacity));
+    }
+
+    /// Go back `frames` frames, or as far as history allows. The nearest
+    /// older state is loaded and the frames after it are run again, with
+    /// the input held at that state. Return the number of frames rewound.
+    pub fn rewind(&mut self, frames: u32) -> u64 {
+        let now = self.frame;
+        let history = match &mut self.history {
+            Some(h) => h,
+            None => return 0,
+        };
+
+        let target = now.saturating_sub(frames as u64);
+        let (frame, state) = match history.restore(target) {
+            Some(s) => s,
+            None => return 0,
+        };
+
+        // States in history were taken with the same ROM
+        self.load_state(&state).unwrap();
+
+        // Run at least a frame so the picture is redrawn
+        let target = target.max(frame + 1);
+        self.replaying = true;
+        while self.frame < target {
+            self.step_frame();
+        }
+        self.replaying = false;
+
+        now.saturating_sub(self.frame)
+    }
+
+    /// Run DMA or CPU until the next event is due.
+    /// A halted CPU skips ahead to the event directly. Return false if
+    /// the CPU is stopped, clocks are paused and no event happens.
+    #[inline]
+    pub fn step_dma_cpu(&mut self) -> bool {
+        let dma = &mut self.dma;
         let cpu = &mut self.cpu;
-        let ppu = &mut self.ppu;
         let bus = &mut self.bus;
-        let timers = &mut self.timers;
-        let dma = &mut self.dma;
         let irqcnt = &mut self.irqcnt;
+        let scheduler = &mut self.scheduler;
 
-        for _ in 0..160 {
-            ppu.hdraw();
+        while !scheduler.pending() {
+            let t = if dma.is_active() {
+                dma.step(irqcnt, bus)
+            } else if irqcnt.sleeping() {
+                if irqcnt.power == Power::Stop {
+                    return false;
+                }
 
-            for _ in 0..960 {
-                Self::step_dma_cpu_timer(dma, cpu, bus, timers, irqcnt);
-            }
+                // Nothing but an event could wake up the CPU
+                scheduler.now = scheduler.next().unwrap();
+                0
+            } else {
+                irqcnt.check(cpu);
+                cpu.step(bus)
+            };
 
-            dma.request_hblank();
-            if ppu.hblank() {
-                irqcnt.request(HBlank);
-            }
+            scheduler.now += t as u64;
+        }
+
+        true
+    }
+
+    /// Return true if the event marks the end of a frame
+    pub fn handle_event(&mut self, event: Event, time: u64) -> bool {
+        use interrupt::Irq::*;
+
+        let ppu = &mut self.ppu;
+        let dma = &mut self.dma;
+        let irqcnt = &mut self.irqcnt;
+        let scheduler = &mut self.scheduler;
 
-            for _ in 0..272 {
-                Self::step_dma_cpu_timer(dma, cpu, bus, timers, irqcnt);
+        match event {
+            Event::HDraw => {
+                if ppu.vcount < 160 {
+                    ppu.hdraw();
+                }
+
+                scheduler.schedule_at(Event::HBlank, time + 960);
             }
+            Event::HBlank => {
+                if ppu.vcount < 160 {
+                    dma.request_hblank();
+                    if ppu.hblank() {
+                        irqcnt.request(HBlank);
+                    }
+                }
 
-            if ppu.increment_vcount() {
-                irqcnt.request(VCount);
+                scheduler.schedule_at(Event::VCount, time + 272);
             }
-        }
+            Event::VCount => {
+                if ppu.increment_vcount() {
+                    irqcnt.request(VCount);
+                }
 
-        dma.request_vblank();
-        if ppu.vblank() {
-            irqcnt.request(VBlank);
-        }
+                scheduler.schedule_at(Event::HDraw, time);
 
-        for _ in 0..68 {
-            for _ in 0..1272 {
-                Self::step_dma_cpu_timer(dma, cpu, bus, timers, irqcnt);
+                match ppu.vcount {
+                    160 => {
+                        dma.request_vblank();
+                        if ppu.vblank() {
+                            irqcnt.request(VBlank);
+                        }
+                    }
+                    228 => {
+                        ppu.rewind();
+                        return true;
+                    }
+                    _ => (),
+                }
             }
-
-            if ppu.increment_vcount() {
-                irqcnt.request(VCount);
+            Event::Timer(i) => {
+                self.timers
+                    .overflow(i, time, scheduler, irqcnt, &mut self.apu, dma);
+            }
+            Event::Dma(i) => {
+                let c = &mut dma.channel[i];
+                c.active = c.enable() && c.start() == 0;
+            }
+            Event::Apu => {
+                let period = self.apu.sample();
+                scheduler.schedule_at(Event::Apu, time + period as u64);
             }
         }
 
-        ppu.rewind();
+        false
     }
 
-    #[inline]
-    pub fn step_dma_cpu_timer(
-        dma: &mut Dma,
-        cpu: &mut Cpu,
-        bus: &mut GbaBus,
-        timers: &mut Timers,
-        irqcnt: &mut IrqController,
-    ) {
-        let t = if dma.is_active() {
-            dma.step(irqcnt, bus)
-        } else {
-            irqcnt.check(cpu);
-            cpu.step(bus)
+    /// Insert a cartridge, backup type is detected unless given
+    pub fn load_rom(&mut self, rom: Vec<u8>, backup: Option<BackupType>) {
+        self.cart = match backup {
+            Some(kind) => Cart::with_backup(rom, kind),
+            None => Cart::with_rom(rom),
         };
-
-        timers.run(t, irqcnt);
     }
 
     pub fn set_callback(&mut self, f: fn()) {
         self.callback = Some(f);
     }
+
+    /// `f` is called at the end of a frame when the rumble motor is switched
+    pub fn set_rumble_callback(&mut self, f: fn(bool)) {
+        self.rumble = Some(f);
+    }
+
+    /// Light level seen by the solar sensor, 0 - dark, 255 - bright
+    pub fn set_light_level(&mut self, level: u8) {
+        if let Some(solar) = self.cart.gpio.as_mut().and_then(|g| g.solar.as_mut()) {
+            solar.level = level;
+        }
+    }
+
+    /// Angular velocity around the axis perpendicular to the screen
+    pub fn set_rotation(&mut self, rotation: i16) {
+        if let Some(gyro) = self.cart.gpio.as_mut().and_then(|g| g.gyro.as_mut()) {
+            gyro.rotation = rotation;
+        }
+    }
+
+    /// Tilt to the right and towards the player
+    pub fn set_tilt(&mut self, x: i16, y: i16) {
+        if let Some(tilt) = &mut self.cart.tilt {
+            tilt.x = x;
+            tilt.y = y;
+        }
+    }
+
+    /// Sound output of every frame is passed to `sink`
+    pub fn set_audio_sink(&mut self, sink: Box<dyn AudioSink>) {
+        self.sink = Some(sink);
+    }
 }
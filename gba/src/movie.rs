@@ -0,0 +1,310 @@
+//! Input movies, KEYINPUT and cartridge sensor readings of every frame
+//! recorded from a save state and played back exactly. Frame buffer hashes
+//! taken at checkpoints tell whether playback has gone out of sync.
+
+use crate::cart::Clock;
+use crate::Gba;
+use std::io::{Error, ErrorKind, Result};
+use util::snapshot::{self, Snapshot};
+
+const MAGIC: &[u8; 4] = b"GBMV";
+const MOVIE_VERSION: u32 = 2;
+
+/// Frames between frame buffer hashes
+const CHECKPOINT_INTERVAL: usize = 60;
+
+/// Input of a frame, sensors missing from the cartridge read 0
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct Input {
+    pub keys: u16,        // KEYINPUT
+    pub light: u8,        // Solar sensor light level
+    pub rotation: i16,    // Gyro sensor angular velocity
+    pub tilt: (i16, i16), // Tilt sensor x and y
+}
+
+util::snapshot!(Input {
+    keys,
+    light,
+    rotation,
+    tilt
+});
+
+pub struct Movie {
+    pub checksum: u32,                  // CRC32 of the ROM
+    pub clock: i64,                     // RTC time at the start, in Unix time
+    pub start: Vec<u8>,                 // Save state playback starts from
+    pub inputs: Vec<Input>,             // Input of every frame
+    pub checkpoints: Vec<(usize, u32)>, // Frame buffer CRC32 after some frames
+
+    first: u64,            // Console frame the movie starts at
+    playing: bool,         // Playing back, otherwise recording
+    position: usize,       // Frames recorded or played
+    desync: Option<usize>, // First checkpoint that failed
+}
+
+impl Movie {
+    fn new(checksum: u32, clock: i64, start: Vec<u8>) -> Self {
+        Self {
+            checksum,
+            clock,
+            start,
+            inputs: Vec::new(),
+            checkpoints: Vec::new(),
+            first: 0,
+            playing: false,
+            position: 0,
+            desync: None,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        MOVIE_VERSION.save_state(&mut out);
+        self.checksum.save_state(&mut out);
+        self.clock.save_state(&mut out);
+        self.start.save_state(&mut out);
+        self.inputs.save_state(&mut out);
+        self.checkpoints.save_state(&mut out);
+        out
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let mut input = data;
+        if snapshot::take(&mut input, 4)? != MAGIC {
+            return Err(Error::new(ErrorKind::InvalidData, "Not a movie"));
+        }
+
+        let mut version = 0u32;
+        version.load_state(&mut input)?;
+        if version != MOVIE_VERSION {
+            let msg = format!("Unsupported movie version {}", version);
+            return Err(Error::new(ErrorKind::InvalidData, msg));
+        }
+
+        let mut movie = Self::new(0, 0, Vec::new());
+        movie.checksum.load_state(&mut input)?;
+        movie.clock.load_state(&mut input)?;
+        movie.start.load_state(&mut input)?;
+        movie.inputs.load_state(&mut input)?;
+        movie.checkpoints.load_state(&mut input)?;
+        Ok(movie)
+    }
+}
+
+impl Gba {
+    /// Start recording from the current state, usually right after power
+    /// on. The RTC runs from `clock` instead of the host clock.
+    pub fn record_movie(&mut self, clock: i64) {
+        self.cart.set_clock(Clock::Fixed(clock));
+        let mut movie = Movie::new(self.cart.checksum, clock, self.save_state());
+        movie.first = self.frame;
+        self.movie = Some(movie);
+    }
+
+    /// Load the starting state of `movie` and play back its input
+    pub fn play_movie(&mut self, mut movie: Movie) -> Result<()> {
+        if movie.checksum != self.cart.checksum {
+            let msg = "Movie was recorded with a different ROM";
+            return Err(Error::new(ErrorKind::InvalidData, msg));
+        }
+
+        self.stop_movie();
+        self.load_state(&movie.start)?;
+        self.cart.set_clock(Clock::Fixed(movie.clock));
+        movie.first = self.frame;
+        movie.position = 0;
+        movie.desync = None;
+        movie.playing = true;
+        self.movie = Some(movie);
+        Ok(())
+    }
+
+    /// Stop recording or playback, returning the movie
+    pub fn stop_movie(&mut self) -> Option<Movie> {
+        self.movie.take()
+    }
+
+    /// True while there is input left to be played back
+    pub fn movie_playing(&self) -> bool {
+        matches!(&self.movie, Some(m) if m.playing && m.position < m.inputs.len())
+    }
+
+    /// Frame at which playback was first found out of sync
+    pub fn movie_desync(&self) -> Option<usize> {
+        self.movie.as_ref().and_then(|m| m.desync)
+    }
+
+    /// Follow a state loaded while a movie is active. Recording goes on from
+    /// the loaded frame with later input dropped, playback jumps to it.
+    /// Fail without change if the frame is outside of the movie.
+    pub(crate) fn movie_seek(&mut self) -> Result<()> {
+        let frame = self.frame;
+        let movie = match &mut self.movie {
+            Some(m) => m,
+            None => return Ok(()),
+        };
+
+        let position = frame
+            .checked_sub(movie.first)
+            .map(|p| p as usize)
+            .filter(|&p| p <= movie.inputs.len());
+        let position = match position {
+            Some(p) => p,
+            None => {
+                let msg = "Save state is outside of the movie";
+                return Err(Error::new(ErrorKind::InvalidInput, msg));
+            }
+        };
+
+        if !movie.playing {
+            movie.inputs.truncate(position);
+            movie.checkpoints.retain(|&(f, _)| f <= position);
+        }
+        movie.position = position;
+        Ok(())
+    }
+
+    /// Feed or record the input of the frame about to run
+    pub(crate) fn movie_input(&mut self) {
+        let movie = match &mut self.movie {
+            Some(m) => m,
+            None => return,
+        };
+
+        if !movie.playing {
+            let cart = &self.cart;
+            let gpio = cart.gpio.as_ref();
+            movie.inputs.push(Input {
+                keys: self.keypad.keyinput,
+                light: gpio.and_then(|g| g.solar.as_ref()).map_or(0, |s| s.level),
+                rotation: gpio.and_then(|g| g.gyro.as_ref()).map_or(0, |g| g.rotation),
+                tilt: cart.tilt.as_ref().map_or((0, 0), |t| (t.x, t.y)),
+            });
+        } else if let Some(&input) = movie.inputs.get(movie.position) {
+            // Values set by the frontend are overridden
+            self.keypad.set_input(input.keys, &mut self.irqcnt);
+            self.set_light_level(input.light);
+            self.set_rotation(input.rotation);
+            self.set_tilt(input.tilt.0, input.tilt.1);
+        }
+    }
+
+    /// Take or compare a checkpoint after a frame is run
+    pub(crate) fn movie_checkpoint(&mut self) {
+        let movie = match &mut self.movie {
+            Some(m) => m,
+            None => return,
+        };
+
+        if movie.playing && movie.position >= movie.inputs.len() {
+            return;
+        }
+
+        movie.position += 1;
+        if movie.position % CHECKPOINT_INTERVAL != 0 {
+            return;
+        }
+
+        let hash = self.frame_hash();
+        let movie = self.movie.as_mut().unwrap();
+        if !movie.playing {
+            movie.checkpoints.push((movie.position, hash));
+        } else if movie.desync.is_none() {
+            let expected = movie.checkpoints.iter().find(|(f, _)| *f == movie.position);
+            if matches!(expected, Some(&(_, h)) if h != hash) {
+                util::warn!("Movie playback out of sync at frame {}", movie.position);
+                movie.desync = Some(movie.position);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn console() -> Box<Gba> {
+        let mut gba = Box::new(Gba::new());
+        gba.init();
+
+        // Copy KEYINPUT to the backdrop color every frame
+        // mov r0, #0x04000000; mov r1, #0x05000000; add r0, r0, #0x100
+        // loop: ldrh r2, [r0, #0x30]; strh r2, [r1]; b loop
+        let code = [
+            0xe3a00301u32,
+            0xe3a01405,
+            0xe2800c01,
+            0xe1d023b0,
+            0xe1c120b0,
+            0xeafffffc,
+        ];
+        let mut rom: Vec<u8> = code.iter().flat_map(|c| c.to_le_bytes()).collect();
+        rom.resize(0x200, 0);
+
+        // Game code of a cartridge with a tilt sensor
+        rom[0xac..0xb0].copy_from_slice(b"KYGE");
+        gba.load_rom(rom, None);
+        gba.cpu.skip_bios();
+        gba
+    }
+
+    #[test]
+    fn record_and_play() {
+        let mut gba = console();
+        gba.record_movie(0);
+        for i in 0..120 {
+            gba.keypad.set_input(0x3ff ^ (i / 7), &mut gba.irqcnt);
+            gba.set_tilt(i as i16, -(i as i16));
+            gba.step_frame();
+        }
+        let end = gba.save_state();
+        let movie = Movie::from_bytes(&gba.stop_movie().unwrap().to_bytes()).unwrap();
+        assert_eq!(movie.checkpoints.len(), 2);
+
+        let mut other = console();
+        other.play_movie(movie).unwrap();
+        while other.movie_playing() {
+            other.step_frame();
+        }
+        assert_eq!(other.movie_desync(), None);
+        assert_eq!(other.save_state(), end);
+    }
+
+    #[test]
+    fn load_while_recording() {
+        let mut gba = console();
+        let before = gba.save_state();
+        gba.step_frame();
+
+        gba.record_movie(0);
+        let mut middle = Vec::new();
+        for i in 0..90 {
+            if i == 30 {
+                middle = gba.save_state();
+            }
+            gba.keypad.set_input(0x3ff ^ (i / 7), &mut gba.irqcnt);
+            gba.step_frame();
+        }
+
+        // Input after the loaded state is recorded again
+        assert!(gba.load_state(&before).is_err());
+        gba.load_state(&middle).unwrap();
+        for _ in 0..40 {
+            gba.keypad.set_input(0x3fe, &mut gba.irqcnt);
+            gba.step_frame();
+        }
+        let end = gba.save_state();
+        let movie = gba.stop_movie().unwrap();
+        assert_eq!(movie.inputs.len(), 70);
+        assert_eq!(movie.checkpoints.len(), 1);
+
+        let mut other = console();
+        other.step_frame();
+        other.play_movie(movie).unwrap();
+        while other.movie_playing() {
+            other.step_frame();
+        }
+        assert_eq!(other.movie_desync(), None);
+        assert_eq!(other.save_state(), end);
+    }
+}
@@ -0,0 +1,223 @@
+//! Rewind history, save states taken every few frames. Only the newest
+//! state is kept whole, older ones are stored as run length compressed
+//! XOR deltas against the state after them, which are mostly zero.
+
+use std::collections::VecDeque;
+
+struct Delta {
+    frame: u64,    // Frame the state was taken at
+    len: usize,    // Length of the state
+    data: Vec<u8>, // Compressed XOR against the next newer state
+}
+
+pub struct Rewind {
+    pub interval: u32,       // Frames between states
+    pub capacity: usize,     // Bytes of deltas kept at most
+    frame: u64,              // Frame of `current`
+    current: Vec<u8>,        // Newest state, empty if none yet
+    deltas: VecDeque<Delta>, // Older states, oldest first
+    size: usize,             // Total bytes of deltas
+}
+
+impl Rewind {
+    pub fn new(interval: u32, capacity: usize) -> Self {
+        Self {
+            interval: interval.max(1),
+            capacity,
+            frame: 0,
+            current: Vec::new(),
+            deltas: VecDeque::new(),
+            size: 0,
+        }
+    }
+
+    /// Whether a state should be taken at the end of `frame`
+    #[inline]
+    pub fn due(&self, frame: u64) -> bool {
+        frame.is_multiple_of(self.interval as u64)
+    }
+
+    /// Add the state taken at `frame`, dropping the oldest ones to fit
+    pub fn push(&mut self, frame: u64, state: Vec<u8>) {
+        if !self.current.is_empty() {
+            let data = compress(&xor(&state, &self.current));
+            self.size += data.len();
+            self.deltas.push_back(Delta {
+                frame: self.frame,
+                len: self.current.len(),
+                data,
+            });
+        }
+
+        while self.size > self.capacity {
+            match self.deltas.pop_front() {
+                Some(d) => self.size -= d.data.len(),
+                None => break,
+            }
+        }
+
+        self.frame = frame;
+        self.current = state;
+    }
+
+    /// Return the newest state taken before `target`, or the oldest one,
+    /// along with its frame. Newer states are discarded.
+    pub fn restore(&mut self, target: u64) -> Option<(u64, Vec<u8>)> {
+        if self.current.is_empty() {
+            return None;
+        }
+
+        while self.frame >= target {
+            let delta = match self.deltas.pop_back() {
+                Some(d) => d,
+                None => break,
+            };
+
+            self.current.resize(delta.len.max(self.current.len()), 0);
+            decompress(&delta.data, &mut self.current);
+            self.current.truncate(delta.len);
+            self.frame = delta.frame;
+            self.size -= delta.data.len();
+        }
+
+        Some((self.frame, self.current.clone()))
+    }
+}
+
+/// XOR of two states, the shorter one padded with zeros
+fn xor(a: &[u8], b: &[u8]) -> Vec<u8> {
+    let mut out = vec![0; a.len().max(b.len())];
+    out[..a.len()].copy_from_slice(a);
+    out.iter_mut().zip(b).for_each(|(o, b)| *o ^= b);
+    out
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(data: &[u8], i: &mut usize) -> usize {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let b = data[*i];
+        *i += 1;
+        value |= ((b & 0x7f) as usize) << shift;
+        if b & 0x80 == 0 {
+            return value;
+        }
+        shift += 7;
+    }
+}
+
+/// Encode as pairs of a zero run and literal bytes, trailing zeros are left out
+fn compress(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut i = 0;
+
+    while i < data.len() {
+        let zeros = data[i..].iter().take_while(|&&b| b == 0).count();
+        i += zeros;
+        if i == data.len() {
+            break;
+        }
+
+        // A literal ends at a run of zeros worth encoding separately
+        let start = i;
+        while i < data.len() && !data[i..].starts_with(&[0; 4]) {
+            i += 1;
+        }
+
+        write_varint(&mut out, zeros);
+        write_varint(&mut out, i - start);
+        out.extend_from_slice(&data[start..i]);
+    }
+
+    out
+}
+
+/// XOR the compressed delta onto `out`
+fn decompress(data: &[u8], out: &mut [u8]) {
+    let mut i = 0;
+    let mut pos = 0;
+
+    while i < data.len() {
+        pos += read_varint(data, &mut i);
+        let len = read_varint(data, &mut i);
+        out[pos..pos + len]
+            .iter_mut()
+            .zip(&data[i..i + len])
+            .for_each(|(o, d)| *o ^= d);
+        pos += len;
+        i += len;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compression() {
+        let mut data = vec![0; 1000];
+        data[3] = 1;
+        data[500..520].iter_mut().for_each(|b| *b = 0xaa);
+
+        let packed = compress(&data);
+        assert!(packed.len() < 40);
+
+        let mut out = vec![0; 1000];
+        decompress(&packed, &mut out);
+        assert_eq!(out, data);
+    }
+
+    #[test]
+    fn restore_and_evict() {
+        let mut rewind = Rewind::new(1, usize::MAX);
+        for frame in 1..=5 {
+            rewind.push(frame, vec![frame as u8; frame as usize]);
+        }
+
+        assert_eq!(rewind.restore(3), Some((2, vec![2; 2])));
+        assert_eq!(rewind.restore(0), Some((1, vec![1; 1])));
+
+        let mut rewind = Rewind::new(1, 0);
+        rewind.push(1, vec![1]);
+        rewind.push(2, vec![2]);
+        assert_eq!(rewind.restore(0), Some((2, vec![2])));
+    }
+
+    #[test]
+    fn rewind_console() {
+        let mut gba = Box::new(crate::Gba::new());
+        gba.init();
+        let mut rom = 0xeafffffeu32.to_le_bytes().to_vec(); // b .
+        rom.resize(0x200, 0);
+        gba.load_rom(rom, None);
+        gba.cpu.skip_bios();
+        gba.enable_rewind(3, 1 << 20);
+
+        let mut states = Vec::new();
+        for _ in 0..10 {
+            gba.step_frame();
+            states.push(gba.save_state());
+        }
+
+        // Frames run again are not played
+        struct Sink;
+        impl crate::AudioSink for Sink {
+            fn write(&mut self, _: &[i16], _: u32) {
+                panic!("Sound output while catching up");
+            }
+        }
+        gba.set_audio_sink(Box::new(Sink));
+
+        assert_eq!(gba.rewind(4), 4);
+        assert_eq!(gba.frame, 6);
+        assert_eq!(gba.save_state(), states[5]);
+    }
+}
@@ -0,0 +1,159 @@
+//! Save states, a snapshot of the whole console prefixed by a header
+//! identifying the format, emulator version and ROM
+
+use crate::Gba;
+use std::io::{Error, ErrorKind, Result};
+use util::snapshot::{self, Snapshot};
+
+const MAGIC: &[u8; 4] = b"GBAR";
+
+/// Bumped whenever the layout of any saved component changes
+pub const STATE_VERSION: u32 = 4;
+
+/// Emulator version the state was saved by, informational only
+const EMULATOR_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+impl Gba {
+    /// Serialize the console, excluding BIOS, ROM and frontend callbacks
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        STATE_VERSION.save_state(&mut out);
+        EMULATOR_VERSION.as_bytes().to_vec().save_state(&mut out);
+        self.cart.checksum.save_state(&mut out);
+
+        self.cpu.save_state(&mut out);
+        self.ppu.save_state(&mut out);
+        self.apu.save_state(&mut out);
+        self.dma.save_state(&mut out);
+        self.bus.save_state(&mut out);
+        self.timers.save_state(&mut out);
+        self.irqcnt.save_state(&mut out);
+        self.keypad.save_state(&mut out);
+        self.cart.save_state(&mut out);
+        self.scheduler.save_state(&mut out);
+        self.frame.save_state(&mut out);
+        self.rumbling.save_state(&mut out);
+        out
+    }
+
+    /// Restore a state saved with the same ROM loaded. The console is left
+    /// as it was if the state turns out to be invalid.
+    pub fn load_state(&mut self, data: &[u8]) -> Result<()> {
+        let mut input = data;
+        self.check_header(&mut input)?;
+
+        // Roll back to the current state if a component fails to load, or
+        // the state does not fit the movie being recorded or played
+        let backup = self.save_state();
+        let result = self
+            .load_components(&mut input)
+            .and_then(|_| self.movie_seek());
+        if let Err(e) = result {
+            let mut input = &backup[..];
+            self.check_header(&mut input).unwrap();
+            self.load_components(&mut input).unwrap();
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    fn check_header(&self, input: &mut &[u8]) -> Result<()> {
+        if snapshot::take(input, 4)? != MAGIC {
+            return Err(Error::new(ErrorKind::InvalidData, "Not a save state"));
+        }
+
+        let mut version = 0u32;
+        version.load_state(input)?;
+        if version != STATE_VERSION {
+            let msg = format!("Unsupported save state version {}", version);
+            return Err(Error::new(ErrorKind::InvalidData, msg));
+        }
+
+        let mut emulator: Vec<u8> = Vec::new();
+        emulator.load_state(input)?;
+
+        let mut checksum = 0u32;
+        checksum.load_state(input)?;
+        if checksum != self.cart.checksum {
+            let msg = "Save state belongs to a different ROM";
+            return Err(Error::new(ErrorKind::InvalidData, msg));
+        }
+
+        Ok(())
+    }
+
+    fn load_components(&mut self, input: &mut &[u8]) -> Result<()> {
+        self.cpu.load_state(input)?;
+        self.ppu.load_state(input)?;
+        self.apu.load_state(input)?;
+        self.dma.load_state(input)?;
+        self.bus.load_state(input)?;
+        self.timers.load_state(input)?;
+        self.irqcnt.load_state(input)?;
+        self.keypad.load_state(input)?;
+        self.cart.load_state(input)?;
+        self.scheduler.load_state(input)?;
+        self.frame.load_state(input)?;
+        self.rumbling.load_state(input)?;
+
+        // The bus points back to the console it is part of
+        self.init();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Gba;
+
+    fn console() -> Box<Gba> {
+        let mut gba = Box::new(Gba::new());
+        gba.init();
+
+        // b .
+        let mut rom = 0xeafffffeu32.to_le_bytes().to_vec();
+        rom.resize(0x200, 0);
+        gba.load_rom(rom, None);
+        gba.cpu.skip_bios();
+        gba
+    }
+
+    #[test]
+    fn roundtrip() {
+        let mut gba = console();
+        gba.step_frame();
+        let state = gba.save_state();
+
+        gba.step_frame();
+        let later = gba.save_state();
+
+        gba.load_state(&state).unwrap();
+        assert_eq!(gba.save_state(), state);
+        gba.step_frame();
+        assert_eq!(gba.save_state(), later);
+    }
+
+    #[test]
+    fn wrong_rom() {
+        let mut gba = console();
+        let state = gba.save_state();
+
+        gba.load_rom(vec![0; 0x200], None);
+        assert!(gba.load_state(&state).is_err());
+        assert!(gba.load_state(&state[..8]).is_err());
+    }
+
+    #[test]
+    fn truncated() {
+        let mut gba = console();
+        let state = gba.save_state();
+
+        gba.step_frame();
+        let later = gba.save_state();
+
+        assert!(gba.load_state(&state[..state.len() - 4]).is_err());
+        assert_eq!(gba.save_state(), later);
+    }
+}
@@ -1,5 +1,9 @@
+use crate::apu::Apu;
+use crate::dma::Dma;
+use crate::event::{Event, Scheduler};
 use crate::interrupt::Irq::*;
 use crate::interrupt::IrqController;
+use util::Snapshot;
 
 pub static PRESCALER: [u16; 4] = [1, 64, 256, 1024];
 
@@ -9,41 +13,81 @@ pub struct Timers {
 
 #[derive(Clone, Debug)]
 pub struct Timer {
+    pub index: usize,    // Timer index 0 - 3
     pub control: u16,    // Raw control bits
     pub reload: u16,     // Initial value on reload
-    pub counter: u16,    // Timer data
-    pub modulo: u16,     // Leftover (< prescaler)
+    pub counter: u16,    // Timer data at `start`
+    pub start: u64,      // Timestamp from which the counter is counting
     pub prescaler: u16,  // 1, 64, 256, 1024
     pub irq_f: bool,     // Interrupt on overflow
     pub cascade_f: bool, // Cascade flag
     pub enable: bool,    // Enable flag
 }
 
+util::snapshot!(Timer {
+    control,
+    reload,
+    counter,
+    start,
+    prescaler,
+    irq_f,
+    cascade_f,
+    enable
+});
+
+impl Snapshot for Timers {
+    fn save_state(&self, out: &mut Vec<u8>) {
+        Timer::save_slice(&self.timer, out);
+    }
+
+    fn load_state(&mut self, input: &mut &[u8]) -> std::io::Result<()> {
+        Timer::load_slice(&mut self.timer, input)
+    }
+}
+
 impl Timers {
     pub fn new() -> Self {
-        Self {
+        let mut t = Self {
             timer: vec![Timer::new(); 4],
+        };
+
+        for i in 0..4 {
+            t.timer[i].index = i;
         }
-    }
 
-    /// There might be some issues when ticks > u16::max_value()
-    pub fn run(&mut self, ticks: i32, irqcnt: &mut IrqController) {
-        let mut times_overflowed = 0;
+        t
+    }
 
-        for (i, timer) in self.timer.iter_mut().enumerate() {
-            if timer.enable {
-                let increment = timer.calculate_increment(ticks as u16, times_overflowed);
+    /// Overflow of a counting timer at `time`, carried into cascading timers
+    pub fn overflow(
+        &mut self,
+        index: usize,
+        time: u64,
+        scheduler: &mut Scheduler,
+        irqcnt: &mut IrqController,
+        apu: &mut Apu,
+        dma: &mut Dma,
+    ) {
+        self.timer[index].reload(time, scheduler);
+
+        for i in index..4 {
+            let timer = &mut self.timer[i];
+
+            // Only the first timer reaches here without overflowing
+            if i != index && !(timer.enable && timer.cascade_f && timer.increment()) {
+                break;
+            }
 
-                times_overflowed = timer.increment_counter(increment);
+            let irq = [Timer0, Timer1, Timer2, Timer3];
 
-                let irq = [Timer0, Timer1, Timer2, Timer3];
+            if timer.irq_f {
+                util::info!("Timer {} generated interrupt", i);
+                irqcnt.request(irq[i])
+            }
 
-                if times_overflowed > 0 && timer.irq_f {
-                    util::info!("Timer {} generated interrupt", i);
-                    irqcnt.request(irq[i])
-                }
-            } else {
-                times_overflowed = 0;
+            // Timer 0 and 1 drive the sound FIFOs
+            if i < 2 {
+                apu.timer_overflow(i, dma);
             }
         }
     }
@@ -52,50 +96,90 @@ impl Timers {
 impl Timer {
     pub fn new() -> Self {
         Self {
+            index: 0,
             control: 0,
             reload: 0,
             counter: 0,
-            prescaler: 0,
-            modulo: 0,
+            start: 0,
+            prescaler: 1,
             irq_f: false,
             cascade_f: false,
             enable: false,
         }
     }
 
-    pub fn calculate_increment(&mut self, ticks_past: u16, times_overflowed: u16) -> u16 {
-        if self.cascade_f {
-            times_overflowed
-        } else {
-            self.modulo += ticks_past;
+    /// Counting on its own, instead of on overflow of the previous timer
+    #[inline]
+    pub fn running(&self) -> bool {
+        self.enable && !self.cascade_f
+    }
 
-            let quotient = self.modulo / self.prescaler;
-            let remainder = self.modulo % self.prescaler;
+    /// Counter value at timestamp `now`
+    pub fn counter_at(&self, now: u64) -> u16 {
+        if !self.running() {
+            return self.counter;
+        }
 
-            self.modulo = remainder;
+        let ticks = (now - self.start) / self.prescaler as u64;
+        let total = self.counter as u64 + ticks;
 
-            quotient
+        if total < 0x10000 {
+            total as u16
+        } else {
+            // Overflow not handled yet
+            let span = 0x10000 - self.reload as u64;
+            (self.reload as u64 + (total - 0x10000) % span) as u16
         }
     }
 
-    /// Add increment to the current counter value, return number of times overflowed
-    pub fn increment_counter(&mut self, increment: u16) -> u16 {
-        let mut remaining = increment;
-        let mut times_overflowed = 0;
-
-        while remaining > 0 {
-            let (value, overflow) = self.counter.overflowing_add(remaining);
-
-            if overflow {
-                remaining -= u16::max_value() - self.counter;
-                times_overflowed += 1;
-                self.counter = self.reload;
-            } else {
-                remaining = 0;
-                self.counter = value;
-            }
+    /// Freeze counter value at `now` to start counting with new settings
+    pub fn sync(&mut self, now: u64) {
+        self.counter = self.counter_at(now);
+        self.start = now;
+    }
+
+    /// Reschedule the overflow event according to current counter value
+    pub fn schedule(&self, scheduler: &mut Scheduler) {
+        scheduler.cancel(Event::Timer(self.index));
+
+        if self.running() {
+            let remaining = (0x10000 - self.counter as u64) * self.prescaler as u64;
+            scheduler.schedule_at(Event::Timer(self.index), self.start + remaining);
         }
+    }
+
+    /// Restart counting from reload value after an overflow at `time`
+    pub fn reload(&mut self, time: u64, scheduler: &mut Scheduler) {
+        self.counter = self.reload;
+        self.start = time;
+        self.schedule(scheduler);
+    }
+
+    /// Count up a cascading timer, return true if overflowed
+    pub fn increment(&mut self) -> bool {
+        let (value, overflow) = self.counter.overflowing_add(1);
+
+        self.counter = if overflow { self.reload } else { value };
+
+        overflow
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counter_at() {
+        let mut scheduler = Scheduler::new();
+        let mut timer = Timer::new();
+
+        timer.reload = 0xff00;
+        timer.set_control(0x0081, &mut scheduler);
+        assert_eq!(timer.counter_at(64 * 0x10), 0xff10);
 
-        times_overflowed
+        // Overflow at 64 * 0x100 cycles
+        assert_eq!(scheduler.next(), Some(64 * 0x100));
+        assert_eq!(timer.counter_at(64 * 0x101), 0xff01);
     }
 }
@@ -0,0 +1,345 @@
+//! Test ROM harness. ROMs are not distributed with the repository, place them
+//! under `tests/fixtures` as described in `tests/fixtures/README.md`.
+//! Suites whose ROMs are missing are skipped.
+
+use gba::{Clock, Gba, BUTTONS};
+use std::path::{Path, PathBuf};
+use util::Bus;
+
+fn fixtures() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+}
+
+fn bios() -> Option<Vec<u8>> {
+    std::fs::read(fixtures().join("gba_bios.bin")).ok()
+}
+
+/// Power on with `rom` inserted, through the BIOS if one is given,
+/// otherwise with the stub that only dispatches interrupts
+fn boot(rom: Vec<u8>, bios: Option<Vec<u8>>) -> Box<Gba> {
+    let mut gba = Box::new(Gba::new());
+    gba.init();
+    gba.load_rom(rom, None);
+    gba.cart.set_clock(Clock::Fixed(0));
+
+    match bios {
+        Some(bios) => gba.bus.bios = bios,
+        None => gba.skip_bios(),
+    }
+
+    gba
+}
+
+/// True if the CPU is spinning on a branch to itself
+fn idle(gba: &Gba) -> bool {
+    let pc = gba.cpu.r(15) as usize;
+
+    // R15 runs ahead of the executing instruction by up to two
+    if gba.cpu.in_thumb_mode() {
+        (1..=2).any(|i| gba.bus.load16(pc - i * 2) == 0xe7fe)
+    } else {
+        (1..=2).any(|i| gba.bus.load32(pc - i * 4) == 0xeafffffe)
+    }
+}
+
+/// A line of `hashes.txt`, `<rom> <frames> <hash> [<frame>:<button>+...]`.
+/// Buttons are pressed for a single frame. A hash of `-` has yet to be
+/// blessed and fails the suite.
+struct Reference {
+    rom: String,
+    frames: u64,
+    hash: Option<u32>,
+    presses: Vec<(u64, u16)>,
+}
+
+fn references() -> Vec<Reference> {
+    let text = std::fs::read_to_string(fixtures().join("hashes.txt")).unwrap();
+    let mut references = Vec::new();
+
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap();
+        let words: Vec<&str> = line.split_whitespace().collect();
+        if words.is_empty() {
+            continue;
+        }
+
+        let presses = words[3..]
+            .iter()
+            .map(|w| {
+                let (frame, buttons) = w.split_once(':').unwrap();
+                let keys = buttons.split('+').fold(0x3ff, |keys, b| {
+                    let i = BUTTONS.iter().position(|n| *n == b).unwrap();
+                    keys & !(1 << i)
+                });
+                (frame.parse().unwrap(), keys)
+            })
+            .collect();
+
+        references.push(Reference {
+            rom: words[0].to_string(),
+            frames: words[1].parse().unwrap(),
+            hash: u32::from_str_radix(words[2], 16).ok(),
+            presses,
+        });
+    }
+
+    references
+}
+
+/// KEYINPUT with only `button` pressed
+fn press(button: &str) -> u16 {
+    0x3ff & !(1 << BUTTONS.iter().position(|b| *b == button).unwrap())
+}
+
+/// Run up to `frames` frames with buttons pressed for a single frame as
+/// listed in `presses`, stopping once `done` holds. Return true if it did.
+fn run(gba: &mut Gba, frames: u64, presses: &[(u64, u16)], done: impl Fn(&Gba) -> bool) -> bool {
+    for frame in 0..frames {
+        if done(gba) {
+            return true;
+        }
+
+        let keys = presses.iter().find(|(f, _)| *f == frame);
+        gba.keypad
+            .set_input(keys.map_or(0x3ff, |k| k.1), &mut gba.irqcnt);
+        gba.step_frame();
+    }
+
+    done(gba)
+}
+
+/// Run `r` and return the hash of its final frame, None if the ROM is
+/// missing. With `until_idle` the run ends a frame after the CPU idles,
+/// `r.frames` is then only a limit and reaching it is reported as false.
+fn run_reference(r: &Reference, bios: Option<Vec<u8>>, until_idle: bool) -> Option<(u32, bool)> {
+    let rom = std::fs::read(fixtures().join(&r.rom)).ok()?;
+
+    let mut gba = boot(rom, bios);
+    let finished = if until_idle {
+        let idled = run(&mut gba, r.frames, &r.presses, idle);
+        gba.step_frame();
+        idled
+    } else {
+        run(&mut gba, r.frames, &r.presses, |_| false);
+        true
+    };
+
+    Some((gba.frame_hash(), finished))
+}
+
+/// Compare the final frame of every reference ROM under `suite`.
+/// Return false if none of them are present.
+fn check_hashes(
+    suite: &str,
+    bios: Option<Vec<u8>>,
+    until_idle: bool,
+    failures: &mut Vec<String>,
+) -> bool {
+    let mut found = false;
+
+    for r in references().iter().filter(|r| r.rom.starts_with(suite)) {
+        let hash = match run_reference(r, bios.clone(), until_idle) {
+            Some((_, false)) => {
+                failures.push(format!("{}: did not finish in {} frames", r.rom, r.frames));
+                found = true;
+                continue;
+            }
+            Some((hash, true)) => hash,
+            None => continue,
+        };
+        found = true;
+
+        match r.hash {
+            Some(expect) if expect != hash => failures.push(format!(
+                "{}: hash {:08x}, expected {:08x}",
+                r.rom, hash, expect
+            )),
+            Some(_) => (),
+            None => failures.push(format!("{}: no reference hash, got {:08x}", r.rom, hash)),
+        }
+    }
+
+    found
+}
+
+fn report(suite: &str, found: bool, failures: Vec<String>) {
+    if !found {
+        println!("{} ROMs not found, skipped", suite);
+    }
+
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+/// jsmolka/gba-tests end in an idle loop, with the number of the failed
+/// test in r12, 0 if all passed
+#[test]
+fn gba_tests() {
+    static ROMS: [&str; 8] = [
+        "arm.gba",
+        "thumb.gba",
+        "memory.gba",
+        "nes.gba",
+        "save/none.gba",
+        "save/sram.gba",
+        "save/flash64.gba",
+        "save/flash128.gba",
+    ];
+
+    let mut found = false;
+    let mut failures = Vec::new();
+
+    for name in ROMS.iter() {
+        let rom = match std::fs::read(fixtures().join("gba-tests").join(name)) {
+            Ok(rom) => rom,
+            Err(_) => continue,
+        };
+        found = true;
+
+        let mut gba = boot(rom, bios());
+        for _ in 0..300 {
+            gba.step_frame();
+        }
+
+        let failed = gba.cpu.r(12);
+        if !idle(&gba) {
+            failures.push(format!("{}: did not finish", name));
+        } else if failed != 0 {
+            failures.push(format!("{}: failed test {}", name, failed));
+        }
+    }
+
+    found |= check_hashes("gba-tests/", bios(), false, &mut failures);
+    report("gba-tests", found, failures);
+}
+
+/// The mGBA suite and FuzzARM call into the BIOS, the stub will not do
+fn bios_or_skip(name: &str) -> Option<Vec<u8>> {
+    let bios = bios();
+    if bios.is_none() {
+        println!("{} needs gba_bios.bin, skipped", name);
+    }
+    bios
+}
+
+/// Every `<passed>/<total>` count in the text logged to SRAM
+fn sram_counts(gba: &Gba) -> Vec<(u32, u32)> {
+    let bytes: Vec<u8> = (0..0x8000)
+        .map(|i| gba.bus.load8(0x0e000000 + i))
+        .take_while(|&b| b != 0 && b != 0xff)
+        .collect();
+
+    String::from_utf8_lossy(&bytes)
+        .split_whitespace()
+        .filter_map(|w| {
+            let (passed, total) = w
+                .trim_matches(|c: char| !c.is_ascii_digit())
+                .split_once('/')?;
+            Some((passed.parse().ok()?, total.parse().ok()?))
+        })
+        .collect()
+}
+
+/// Test groups run from the top of the mGBA suite menu, the later ones
+/// need input or a look at the screen
+const MGBA_GROUPS: u64 = 4;
+/// Frames a group may take to log its result after being started
+const MGBA_FRAMES: u64 = 3600;
+
+/// The mGBA suite logs the pass count of each group it runs to SRAM.
+/// Every group is started on a fresh console by moving down the menu,
+/// once it is shown after the BIOS intro, and pressing A.
+#[test]
+fn mgba_suite() {
+    let rom = match std::fs::read(fixtures().join("mgba-suite/suite.gba")) {
+        Ok(rom) => rom,
+        Err(_) => return report("mgba-suite", false, Vec::new()),
+    };
+    let bios = match bios_or_skip("mgba-suite") {
+        Some(bios) => bios,
+        None => return,
+    };
+
+    let mut failures = Vec::new();
+
+    for group in 0..MGBA_GROUPS {
+        let mut presses: Vec<(u64, u16)> =
+            (0..group).map(|i| (120 + i * 20, press("down"))).collect();
+        let start = 120 + group * 20;
+        presses.push((start, press("a")));
+
+        let mut gba = boot(rom.clone(), Some(bios.clone()));
+        let logged = run(&mut gba, start + 1 + MGBA_FRAMES, &presses, |gba| {
+            !sram_counts(gba).is_empty()
+        });
+
+        let counts = sram_counts(&gba);
+        if !logged {
+            failures.push(format!(
+                "group {}: no result logged in {} frames",
+                group, MGBA_FRAMES
+            ));
+        } else if counts.iter().any(|(passed, total)| passed != total) {
+            failures.push(format!("group {}: passed {:?}", group, counts));
+        }
+    }
+
+    report("mgba-suite", true, failures);
+}
+
+/// FuzzARM ROMs idle after their last iteration or at the first mismatch,
+/// showing the result on screen. `hashes.txt` holds the passing screens.
+#[test]
+fn fuzzarm() {
+    let bios = match bios_or_skip("FuzzARM") {
+        Some(bios) => bios,
+        None => return,
+    };
+
+    let mut failures = Vec::new();
+    let found = check_hashes("fuzzarm/", Some(bios), true, &mut failures);
+    report("FuzzARM", found, failures);
+}
+
+/// Rewrite `hashes.txt` with the hashes of the present ROMs. Check the
+/// result screens first, e.g. with `headless --png`, then run
+///     cargo test -p gba --test roms -- --ignored bless
+#[test]
+#[ignore]
+fn bless() {
+    let path = fixtures().join("hashes.txt");
+    let text = std::fs::read_to_string(&path).unwrap();
+    let mut references = references().into_iter();
+    let mut lines = Vec::new();
+
+    for line in text.lines() {
+        if line.split('#').next().unwrap().trim().is_empty() {
+            lines.push(line.to_string());
+            continue;
+        }
+
+        let r = references.next().unwrap();
+        if !fixtures().join(&r.rom).exists() {
+            lines.push(line.to_string());
+            continue;
+        }
+
+        let bios = if r.rom.starts_with("gba-tests/") {
+            bios()
+        } else {
+            Some(bios().expect("gba_bios.bin is needed"))
+        };
+
+        let mut words: Vec<String> = line.split_whitespace().map(String::from).collect();
+        let until_idle = r.rom.starts_with("fuzzarm/");
+        match run_reference(&r, bios, until_idle) {
+            Some((hash, true)) => {
+                println!("{} {:08x}", r.rom, hash);
+                words[2] = format!("{:08x}", hash);
+            }
+            _ => println!("{} did not finish, kept", r.rom),
+        }
+        lines.push(words.join(" "));
+    }
+
+    std::fs::write(&path, lines.join("\n") + "\n").unwrap();
+}
@@ -0,0 +1,193 @@
+//! Run a ROM without a window for a number of frames, then print the hash
+//! of the final frame and optionally save it as PNG, along with the sound
+//! output as a wave file. Meant for CI.
+//!
+//! Exit status is 0 on success, 1 if the hash does not match `--expect`,
+//! and 2 on bad arguments or files.
+
+mod png;
+
+use gba::BUTTONS;
+use std::process::exit;
+use util::Color;
+
+struct Options {
+    rom: String,
+    frames: u64,
+    bios: Option<String>,
+    input: Option<String>,
+    movie: Option<String>,
+    png: Option<String>,
+    wav: Option<(String, u32)>,
+    expect: Option<u32>,
+}
+
+fn main() {
+    let options = parse_args().unwrap_or_else(|e| fail(&e));
+
+    let rom = read(&options.rom);
+    let mut gba = Box::new(gba::Gba::new());
+    gba.init();
+    gba.load_rom(rom, None);
+
+    // Without a BIOS, start right at the cartridge entry point with a stub
+    // that only dispatches interrupts
+    match &options.bios {
+        Some(path) => gba.bus.bios = read(path),
+        None => gba.skip_bios(),
+    }
+
+    // Host clock would make runs differ
+    gba.cart.set_clock(gba::Clock::Fixed(0));
+
+    let script = match &options.input {
+        Some(path) => {
+            parse_script(&String::from_utf8_lossy(&read(path))).unwrap_or_else(|e| fail(&e))
+        }
+        None => Vec::new(),
+    };
+
+    if let Some(path) = &options.movie {
+        let movie = gba::Movie::from_bytes(&read(path)).unwrap_or_else(|e| fail(&e.to_string()));
+        gba.play_movie(movie)
+            .unwrap_or_else(|e| fail(&e.to_string()));
+    }
+
+    if let Some((path, rate)) = &options.wav {
+        let wav = gba::WavWriter::create(path, *rate)
+            .unwrap_or_else(|e| fail(&format!("{}: {}", path, e)));
+        gba.set_audio_sink(Box::new(wav));
+    }
+
+    for frame in 0..options.frames {
+        if let Some(&(_, keys)) = script.iter().rev().find(|(f, _)| *f <= frame) {
+            gba.keypad.set_input(keys, &mut gba.irqcnt);
+        }
+        gba.step_frame();
+    }
+
+    if let Some(frame) = gba.movie_desync() {
+        eprintln!("Movie playback went out of sync at frame {}", frame);
+    }
+
+    if let (Some(sink), Some((path, _))) = (gba.sink.take(), &options.wav) {
+        if let Err(e) = sink.finish() {
+            fail(&format!("{}: {}", path, e));
+        }
+    }
+
+    if let Some(path) = &options.png {
+        let pixels: Vec<u32> = gba.ppu.buffer.iter().map(|p| p.to_rgb24()).collect();
+        let result = std::fs::File::create(path)
+            .map(std::io::BufWriter::new)
+            .and_then(|mut f| png::write(&mut f, 240, 160, &pixels));
+        if let Err(e) = result {
+            fail(&format!("{}: {}", path, e));
+        }
+    }
+
+    let hash = gba.frame_hash();
+    println!("{:08x}", hash);
+
+    match options.expect {
+        Some(expect) if expect != hash => {
+            eprintln!("Expected {:08x}", expect);
+            exit(1)
+        }
+        _ => exit(0),
+    }
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options {
+        rom: String::new(),
+        frames: 0,
+        bios: None,
+        input: None,
+        movie: None,
+        png: None,
+        wav: None,
+        expect: None,
+    };
+
+    for arg in std::env::args().skip(1) {
+        let (name, value) = match arg.split_once('=') {
+            Some((n, v)) => (n, v.to_string()),
+            None if !arg.starts_with("--") && options.rom.is_empty() => {
+                options.rom = arg;
+                continue;
+            }
+            None => return Err(format!("Unexpected argument {}", arg)),
+        };
+
+        match name {
+            "--frames" => options.frames = value.parse().map_err(|_| "Invalid frame count")?,
+            "--bios" => options.bios = Some(value),
+            "--input" => options.input = Some(value),
+            "--movie" => options.movie = Some(value),
+            "--png" => options.png = Some(value),
+            "--wav" => {
+                let (path, rate) = match value.split_once(',') {
+                    Some((p, r)) => (p, r.parse().map_err(|_| "Invalid sample rate")?),
+                    None => (value.as_str(), 32768),
+                };
+                if rate == 0 {
+                    return Err("Invalid sample rate".to_string());
+                }
+                options.wav = Some((path.to_string(), rate))
+            }
+            "--expect" => {
+                let hash = u32::from_str_radix(&value, 16).map_err(|_| "Invalid hash")?;
+                options.expect = Some(hash)
+            }
+            _ => return Err(format!("Unknown option {}", name)),
+        }
+    }
+
+    if options.rom.is_empty() {
+        return Err("No ROM given".to_string());
+    }
+
+    Ok(options)
+}
+
+/// Input script, each line is a frame number followed by the buttons held
+/// from that frame on, e.g. `120 start` or `300 a right`. `#` starts a comment.
+fn parse_script(text: &str) -> Result<Vec<(u64, u16)>, String> {
+    let mut script = Vec::new();
+
+    for (n, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap();
+        let mut words = line.split_whitespace();
+        let frame = match words.next() {
+            Some(w) => w
+                .parse()
+                .map_err(|_| format!("Line {}: invalid frame", n + 1))?,
+            None => continue,
+        };
+
+        // KEYINPUT bits are cleared while a button is pressed
+        let mut keys = 0x3ff;
+        for w in words {
+            let lower = w.to_lowercase();
+            match BUTTONS.iter().position(|b| *b == lower) {
+                Some(i) => keys &= !(1 << i),
+                None => return Err(format!("Line {}: unknown button {}", n + 1, w)),
+            }
+        }
+        script.push((frame, keys));
+    }
+
+    script.sort_by_key(|&(frame, _)| frame);
+    Ok(script)
+}
+
+fn read(path: &str) -> Vec<u8> {
+    std::fs::read(path).unwrap_or_else(|e| fail(&format!("{}: {}", path, e)))
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    eprintln!("usage: headless <rom> --frames=<n> [--bios=<file>] [--input=<script>|--movie=<file>] [--png=<file>] [--wav=<file>[,rate]] [--expect=<hash>]");
+    exit(2)
+}
@@ -0,0 +1,66 @@
+//! Minimal PNG encoder, 8 bit RGB with uncompressed deflate blocks
+
+use std::io::{Result, Write};
+
+fn chunk(w: &mut impl Write, kind: &[u8; 4], data: &[u8]) -> Result<()> {
+    let mut crc_data = kind.to_vec();
+    crc_data.extend_from_slice(data);
+
+    w.write_all(&(data.len() as u32).to_be_bytes())?;
+    w.write_all(&crc_data)?;
+    w.write_all(&util::crc32(&crc_data).to_be_bytes())
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (a, b) = data.iter().fold((1u32, 0u32), |(a, b), &d| {
+        let a = (a + d as u32) % 65521;
+        (a, (b + a) % 65521)
+    });
+    b << 16 | a
+}
+
+/// Wrap `data` in a zlib stream of stored blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let mut blocks = data.chunks(0xffff).peekable();
+
+    // An empty stream still needs a final block
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let len = block.len() as u16;
+        out.push(last as u8);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+/// Write RGB24 pixels, `0x00rrggbb` each, as a PNG image
+pub fn write(w: &mut impl Write, width: u32, height: u32, pixels: &[u32]) -> Result<()> {
+    w.write_all(b"\x89PNG\r\n\x1a\n")?;
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    header.extend_from_slice(&[8, 2, 0, 0, 0]); // 8 bit RGB, no interlace
+    chunk(w, b"IHDR", &header)?;
+
+    // Each scanline is prefixed by filter type 0
+    let mut raw = Vec::new();
+    for line in pixels.chunks(width as usize) {
+        raw.push(0);
+        for p in line {
+            raw.extend_from_slice(&p.to_be_bytes()[1..]);
+        }
+    }
+    chunk(w, b"IDAT", &zlib_stored(&raw))?;
+
+    chunk(w, b"IEND", &[])
+}
@@ -23,6 +23,22 @@ pub struct Background {
     pub internal: (i32, i32),
 }
 
+snapshot!(Background {
+    bgcnt,
+    priority,
+    tile_b,
+    map_b,
+    mosaic_f,
+    palette_f,
+    wrap_f,
+    size_r,
+    hscroll,
+    vscroll,
+    matrix,
+    coord,
+    internal
+});
+
 impl Background {
     pub fn new() -> Self {
         Self {
@@ -187,16 +203,30 @@ impl Ppu {
         ret
     }
 
+    /// Line sampled by background `index` and width of its mosaic blocks
+    #[inline]
+    pub fn bg_mosaic(&self, index: usize) -> (u16, u32) {
+        if self.background[index].mosaic_f {
+            let m = &self.mosaic;
+            (m.bg_line, m.bg_width() as u32)
+        } else {
+            (self.vcount, 1)
+        }
+    }
+
     pub fn draw_text_background(&mut self, index: usize) {
         let bg = &self.background[index];
-        let vcount = self.vcount;
         let window = &self.window;
+        let (line, size) = self.bg_mosaic(index);
         let (width, height) = self.get_background_dimension(index);
 
         // Vertical wrap around
-        let line_n = (vcount.wrapping_add(bg.vscroll)) as u32 % height;
+        let line_n = (line.wrapping_add(bg.vscroll)) as u32 % height;
+
+        for x in 0..240 {
+            // Horizontal wrap around
+            let i = (x - x % size + bg.hscroll as u32) % width;
 
-        for i in 0..width {
             let tile_x = i / 8;
             let tile_y = line_n / 8;
             let mut pixel_x = i % 8;
@@ -217,12 +247,9 @@ impl Ppu {
             };
 
             let palette_entry = self.tile_data(bg.palette_f, bg.tile_b, tile_n, pixel_x, pixel_y);
-
-            // Horizontal wrap around
-            let x = i.wrapping_sub(bg.hscroll as u32) % width;
             let color = self.bg_palette(bg.palette_f, palette_n, palette_entry);
 
-            let layer = &mut self.layer[bg.priority as usize];
+            let layer = &mut self.layer[index];
             layer.paint(x, color, window, index);
         }
     }
@@ -231,11 +258,18 @@ impl Ppu {
         let bg = &self.background[index];
         let vram = &self.vram;
         let window = &self.window;
+        let (line, size) = self.bg_mosaic(index);
         let (width, height) = self.get_background_dimension(index);
 
-        for i in 0..width {
-            let mut text_x = (bg.matrix.0 * i as i32 + bg.internal.0) >> 8;
-            let mut text_y = (bg.matrix.2 * i as i32 + bg.internal.1) >> 8;
+        // Reference point of the sampled line
+        let dy = (self.vcount - line) as i32;
+        let ref_x = bg.internal.0 - bg.matrix.1 * dy;
+        let ref_y = bg.internal.1 - bg.matrix.3 * dy;
+
+        for x in 0..240 {
+            let i = (x - x % size) as i32;
+            let mut text_x = (bg.matrix.0 * i + ref_x) >> 8;
+            let mut text_y = (bg.matrix.2 * i + ref_y) >> 8;
 
             // TODO: Refactor into macro
             if out_of_bound(text_x, width) {
@@ -265,43 +299,46 @@ impl Ppu {
             let palette_entry = self.tile_data(true, bg.tile_b, tile_n, pixel_x, pixel_y);
             let color = self.bg_palette(true, 0, palette_entry);
 
-            let layer = &mut self.layer[bg.priority as usize];
-            layer.paint(i, color, window, index);
+            let layer = &mut self.layer[index];
+            layer.paint(x, color, window, index);
         }
     }
 
     pub fn draw_bitmap_3(&mut self) {
-        let line_n = self.vcount as u32;
+        let (line, size) = self.bg_mosaic(2);
+        let line_n = line as u32;
         let window = &self.window;
 
         for x in 0..240 {
-            let pixel = self.vram16((line_n * 240 + x) * 2);
-            self.layer[0].paint(x, pixel, window, 2);
+            let pixel = self.vram16((line_n * 240 + x - x % size) * 2);
+            self.layer[2].paint(x, pixel, window, 2);
         }
     }
 
     pub fn draw_bitmap_4(&mut self) {
         let start = if self.flip { 0xa000 } else { 0 };
-        let line_n = self.vcount as u32;
+        let (line, size) = self.bg_mosaic(2);
+        let line_n = line as u32;
 
         for x in 0..240 {
-            let palette_entry = self.vram8(start + line_n * 240 + x);
+            let palette_entry = self.vram8(start + line_n * 240 + x - x % size);
             let color = self.bg_palette(true, 0, palette_entry as u32);
-            self.layer[0].paint(x, color, &self.window, 2);
+            self.layer[2].paint(x, color, &self.window, 2);
         }
     }
 
     pub fn draw_bitmap_5(&mut self) {
         let start = if self.flip { 0xa000 } else { 0 };
-        let line_n = self.vcount as u32;
+        let (line, size) = self.bg_mosaic(2);
+        let line_n = line as u32;
         let window = &self.window;
-        if line_n > 127 {
+        if self.vcount > 127 {
             return;
         }
 
         for x in 0..160 {
-            let pixel = self.vram16(start + (line_n * 160 + x) * 2);
-            self.layer[0].paint(x, pixel, window, 2);
+            let pixel = self.vram16(start + (line_n * 160 + x - x % size) * 2);
+            self.layer[2].paint(x, pixel, window, 2);
         }
     }
 }
@@ -321,5 +358,5 @@ pub fn wrap_around(a: i32, max: u32) -> i32 {
         b += max as i32;
     }
 
-    b as i32
+    b
 }
@@ -0,0 +1,122 @@
+use util::*;
+
+/// Color special effects
+#[derive(Clone, Copy)]
+pub struct Blend {
+    pub bldcnt: u16,   // Targets and effect
+    pub bldalpha: u16, // Coefficients of first and second target
+    pub bldy: u16,     // Brightness coefficient, write only
+}
+
+snapshot!(Blend {
+    bldcnt,
+    bldalpha,
+    bldy
+});
+
+impl Blend {
+    pub fn new() -> Self {
+        Self {
+            bldcnt: 0,
+            bldalpha: 0,
+            bldy: 0,
+        }
+    }
+
+    /// 0 - none, 1 - alpha blending, 2 - brighten, 3 - darken
+    #[inline]
+    pub fn effect(&self) -> u32 {
+        self.bldcnt.bits(7, 6)
+    }
+
+    #[inline]
+    pub fn first_target(&self, id: u8) -> bool {
+        self.bldcnt.bit(id as u32)
+    }
+
+    #[inline]
+    pub fn second_target(&self, id: u8) -> bool {
+        self.bldcnt.bit(id as u32 + 8)
+    }
+
+    /// Mix two colors by EVA / 16 and EVB / 16
+    pub fn alpha(&self, a: u16, b: u16) -> u16 {
+        let eva = self.bldalpha.bits(4, 0).min(16);
+        let evb = self.bldalpha.bits(12, 8).min(16);
+
+        map_channels(|i| {
+            let c = (channel(a, i) * eva + channel(b, i) * evb) >> 4;
+            c.min(31)
+        })
+    }
+
+    /// Move a color towards white by EVY / 16
+    pub fn brighten(&self, a: u16) -> u16 {
+        let evy = self.bldy.bits(4, 0).min(16);
+        map_channels(|i| {
+            let c = channel(a, i);
+            c + (((31 - c) * evy) >> 4)
+        })
+    }
+
+    /// Move a color towards black by EVY / 16
+    pub fn darken(&self, a: u16) -> u16 {
+        let evy = self.bldy.bits(4, 0).min(16);
+        map_channels(|i| {
+            let c = channel(a, i);
+            c - ((c * evy) >> 4)
+        })
+    }
+}
+
+impl Blend {
+    pub fn get_bldcnt(&self) -> u16 {
+        self.bldcnt
+    }
+
+    pub fn get_bldalpha(&self) -> u16 {
+        self.bldalpha
+    }
+
+    pub fn set_bldcnt(&mut self, value: u16) {
+        self.bldcnt = value & 0x3fff;
+    }
+
+    pub fn set_bldalpha(&mut self, value: u16) {
+        self.bldalpha = value & 0x1f1f;
+    }
+
+    pub fn set_bldy(&mut self, value: u16) {
+        self.bldy = value & 0x1f;
+    }
+}
+
+#[inline]
+fn channel(color: u16, i: u32) -> u32 {
+    (color as u32 >> (i * 5)) & 0x1f
+}
+
+#[inline]
+fn map_channels(f: impl Fn(u32) -> u32) -> u16 {
+    (f(0) | f(1) << 5 | f(2) << 10) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn effects() {
+        let mut blend = Blend::new();
+        blend.set_bldalpha(0x0808);
+        assert_eq!(blend.alpha(0x001f, 0x7c00), 0x3c0f);
+
+        // Coefficients saturate at 16, channels at 31
+        blend.set_bldalpha(0x1f1f);
+        assert_eq!(blend.alpha(0x7fff, 0x7fff), 0x7fff);
+
+        blend.set_bldy(8);
+        assert_eq!(blend.brighten(0x0000), 0x3def);
+        assert_eq!(blend.darken(0x7fff), 0x4210);
+    }
+}
@@ -1,15 +1,25 @@
 use super::window::Window;
 use super::TRANSPARENT;
 
+/// Source of a pixel, index into the target bits of BLDCNT
+pub const OBJ: u8 = 4;
+pub const BACKDROP: u8 = 5;
+/// Set along with OBJ for semi-transparent sprites
+pub const SEMI: u8 = 0x08;
+
 #[derive(Clone, Copy)]
 pub struct Layer {
     pub pixel: [u16; 240],
+    pub priority: [u8; 240], // Priority of each sprite pixel
+    pub semi: [bool; 240],   // Pixel of a semi-transparent sprite
 }
 
 impl Layer {
     pub fn new() -> Self {
         Self {
             pixel: [TRANSPARENT; 240],
+            priority: [0; 240],
+            semi: [false; 240],
         }
     }
 
@@ -23,9 +33,34 @@ impl Layer {
         }
     }
 
-    pub fn clear(&mut self) {
-        for p in self.pixel.iter_mut() {
-            *p = TRANSPARENT;
+    /// Sprites are drawn in OAM order, a pixel is only replaced by one of
+    /// higher priority
+    pub fn paint_sprite(&mut self, x: u32, color: u16, window: &Window, priority: u32, semi: bool) {
+        if color == TRANSPARENT || x >= 240 {
+            return;
+        }
+
+        let x = x as usize;
+        let priority = priority as u8;
+        if self.pixel[x] != TRANSPARENT && priority >= self.priority[x] {
+            return;
+        }
+
+        if window.get_display_flag(x as u32, OBJ as usize) {
+            self.pixel[x] = color;
+            self.priority[x] = priority;
+            self.semi[x] = semi;
+        }
+    }
+
+    /// Mark an opaque pixel regardless of windows, used for the OBJ window
+    pub fn mask(&mut self, x: u32, color: u16) {
+        if color != TRANSPARENT && x < 240 {
+            self.pixel[x as usize] = color;
         }
     }
+
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
 }
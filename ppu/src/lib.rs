@@ -1,8 +1,10 @@
 #![allow(clippy::new_without_default)]
 
 mod background;
+mod blend;
 mod io;
 mod layer;
+mod mosaic;
 mod oam;
 mod sprite;
 mod window;
@@ -10,7 +12,9 @@ mod window;
 use util::*;
 
 use background::Background;
-use layer::Layer;
+use blend::Blend;
+use layer::{Layer, BACKDROP, OBJ, SEMI};
+use mosaic::Mosaic;
 use oam::Oam;
 use sprite::Sprite;
 use window::Window;
@@ -35,11 +39,32 @@ pub struct Ppu {
 
     pub background: [Background; 4], // Background 0 - 3
     pub window: Window,
+    pub blend: Blend,
+    pub mosaic: Mosaic,
 
-    pub layer: [Layer; 5], // Layer 0 - 3, and an extra layer for backdrop
+    pub layer: [Layer; 5],        // Background 0 - 3, and sprites
+    pub objwin: Layer,            // Opaque pixels of window sprites
     pub buffer: [u16; 240 * 160], // Frame buffer, 240 * 160
 }
 
+// Layers and frame buffer are output of rendering
+snapshot!(Ppu {
+    dispcnt,
+    dispstat,
+    vcount,
+    mode,
+    flip,
+    sequential,
+    fblank,
+    palette,
+    vram,
+    oam,
+    background,
+    window,
+    blend,
+    mosaic
+});
+
 impl Ppu {
     pub fn new() -> Self {
         Self {
@@ -57,8 +82,11 @@ impl Ppu {
 
             background: [Background::new(); 4],
             window: Window::new(),
+            blend: Blend::new(),
+            mosaic: Mosaic::new(),
 
             layer: [Layer::new(); 5],
+            objwin: Layer::new(),
             buffer: [0; 240 * 160],
         }
     }
@@ -69,16 +97,11 @@ impl Ppu {
         }
         assert!(self.vcount < 160);
 
-        // Setup backdrop color
-        let bd = self.backdrop();
-        for p in self.layer[4].pixel.iter_mut() {
-            *p = bd
-        }
-
-        for i in 0..4 {
-            self.layer[i].clear();
+        for layer in self.layer.iter_mut() {
+            layer.clear();
         }
 
+        self.mosaic.next_line(self.vcount);
         self.draw_window();
 
         self.draw_background();
@@ -126,18 +149,63 @@ impl Ppu {
 
     pub fn combine_layers(&mut self) {
         let n = self.vcount as usize * 240;
-        let line = &mut self.buffer[n..n + 240];
-
-        for (i, l) in line.iter_mut().enumerate() {
-            for j in 0..5 {
-                let pixel = self.layer[j].pixel[i];
+        let backdrop = (self.backdrop(), BACKDROP);
+
+        for x in 0..240 {
+            // Topmost two opaque pixels by (priority, order), sprites go
+            // before backgrounds of the same priority, then lower index first
+            let mut top = [(u8::MAX, backdrop); 2];
+            for (i, layer) in self.layer.iter().enumerate() {
+                let color = layer.pixel[x];
+                if color == TRANSPARENT {
+                    continue;
+                }
 
-                // Render the topmost opaque color
-                if pixel != TRANSPARENT {
-                    *l = pixel;
-                    break;
+                let (priority, rank, id) = if i == OBJ as usize {
+                    let semi = if layer.semi[x] { SEMI } else { 0 };
+                    (layer.priority[x], 0, OBJ | semi)
+                } else {
+                    let priority = self.background[i].priority as u8;
+                    (priority, i as u8 + 1, i as u8)
+                };
+                let order = (priority << 3) | rank;
+
+                if order < top[0].0 {
+                    top[1] = top[0];
+                    top[0] = (order, (color, id));
+                } else if order < top[1].0 {
+                    top[1] = (order, (color, id));
                 }
             }
+
+            // Nothing is below the backdrop
+            let below = Some(top[1].1).filter(|_| top[0].0 != u8::MAX);
+            self.buffer[n + x] = self.special_effect(x, top[0].1, below);
+        }
+    }
+
+    /// Apply BLDCNT to the topmost pixel `a`, with `b` right below it
+    pub fn special_effect(&self, x: usize, a: (u16, u8), b: Option<(u16, u8)>) -> u16 {
+        let blend = &self.blend;
+        let (color, id) = a;
+        let second = b.filter(|&(_, id)| blend.second_target(id & !SEMI));
+
+        // Semi-transparent sprites are blended whatever the effect is
+        if let (true, Some((below, _))) = (id & SEMI != 0, second) {
+            return blend.alpha(color, below);
+        }
+
+        if !self.window.get_display_flag(x as u32, BACKDROP as usize)
+            || !blend.first_target(id & !SEMI)
+        {
+            return color;
+        }
+
+        match (blend.effect(), second) {
+            (1, Some((below, _))) => blend.alpha(color, below),
+            (2, _) => blend.brighten(color),
+            (3, _) => blend.darken(color),
+            _ => color,
         }
     }
 
@@ -154,50 +222,42 @@ impl Ppu {
     }
 
     pub fn draw_sprites(&mut self) {
-        for i in (0..self.oam.sprite.len()).rev() {
-            self.draw_sprite(i);
+        for i in 0..self.sprite_limit() {
+            if self.oam.sprite[i].mode != 2 {
+                self.draw_sprite(i);
+            }
         }
     }
 
     pub fn draw_window(&mut self) {
-        let window = &mut self.window;
-        window.clear();
+        self.window.clear();
 
         if self.dispcnt.bits(15, 13) > 0 {
-            window.draw_winout();
+            self.window.draw_winout();
         }
 
-        // if self.dispcnt.bit(15)
-        // {
-        //     let mut layer = Layer::new();
-        //     let mut dummy = Window::new(); // Dummy window to let all sprite be drawn
-        //     dummy.clear();
-
-        //     for sprite in self.sprite.iter_mut().rev()
-        //     {
-        //         memory.update_sprite(sprite);
-
-        //         if sprite.mode == 0b10 // Window mode
-        //         {
-        //             sprite.draw(self.vcount as u32, self.sequential, &dummy, &mut layer, memory);
-        //         }
-        //     }
+        // Window sprites are only drawn into the OBJ window mask
+        if self.dispcnt.bit(15) {
+            self.objwin.clear();
+            for i in 0..self.sprite_limit() {
+                if self.oam.sprite[i].mode == 2 {
+                    self.draw_sprite(i);
+                }
+            }
 
-        //     window.draw_objwin(&layer)
-        // }
+            self.window.draw_objwin(&self.objwin);
+        }
 
         if self.dispcnt.bit(14) {
-            window.draw_winin(self.vcount as u32, 1);
+            self.window.draw_winin(self.vcount as u32, 1);
         }
 
         if self.dispcnt.bit(13) {
-            window.draw_winin(self.vcount as u32, 0);
+            self.window.draw_winin(self.vcount as u32, 0);
         }
     }
 
     pub fn draw_mode_0(&mut self) {
-        // Background is drawn in reverse order to give
-        // precedence to ones with lower index.
         if self.dispcnt.bit(11) {
             self.draw_text_background(3)
         }
@@ -240,3 +300,78 @@ impl Ppu {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layer_priority() {
+        let mut ppu = Ppu::new();
+        let mut window = Window::new();
+        window.clear();
+        ppu.palette[0] = 0x7fff;
+        ppu.background[0].set_control(1);
+        ppu.background[1].set_control(1);
+
+        // Lower background index wins
+        ppu.layer[1].paint(0, 1, &window, 1);
+        ppu.layer[0].paint(0, 2, &window, 0);
+
+        // Sprites win against backgrounds of the same priority
+        ppu.layer[1].paint(1, 1, &window, 1);
+        ppu.layer[4].paint_sprite(1, 3, &window, 1, false);
+
+        // Lower priority value wins regardless of layer
+        ppu.layer[1].paint(2, 1, &window, 1);
+        ppu.layer[4].paint_sprite(2, 4, &window, 2, false);
+
+        // Lower OAM index wins among sprites
+        ppu.layer[4].paint_sprite(3, 5, &window, 3, false);
+        ppu.layer[4].paint_sprite(3, 6, &window, 3, false);
+
+        ppu.combine_layers();
+        assert_eq!(ppu.buffer[..5], [2, 3, 1, 5, 0x7fff]);
+    }
+
+    #[test]
+    fn sprite_budget() {
+        // 8x8 sprites at the top left, 8 cycles each
+        let mut ppu = Ppu::new();
+        assert_eq!(ppu.sprite_limit(), 128);
+
+        ppu.set_dispcnt(0x0020);
+        assert_eq!(ppu.sprite_limit(), 119);
+
+        // Double size affine 64x64 sprite takes 10 + 128 * 2 cycles
+        ppu.oam.sprite[0].set_attr0(0x0300);
+        ppu.oam.sprite[0].set_attr1(0xc000);
+        assert_eq!(ppu.sprite_limit(), 87);
+    }
+
+    #[test]
+    fn obj_window() {
+        let mut ppu = Ppu::new();
+        ppu.set_dispcnt(0x9140);
+        ppu.palette[0] = 0x7c00;
+        ppu.palette[1] = 0x001f;
+
+        // Opaque tile 0 for BG 0, with its map at 0x4000, and for sprites
+        ppu.vram[..32].fill(0x11);
+        ppu.vram[0x10000..0x10020].fill(0x11);
+        ppu.background[0].set_control(0x0800);
+
+        // Only BG 0 is shown inside the OBJ window, nothing outside
+        ppu.window.set_winout(0x0100);
+        ppu.oam.sprite[0].set_attr0(0x0800);
+        ppu.oam.sprite[0].set_attr1(0x0010);
+        for sprite in ppu.oam.sprite[1..].iter_mut() {
+            sprite.set_attr0(0x0200);
+        }
+
+        ppu.hdraw();
+        assert_eq!(ppu.buffer[8..16], [0x7c00; 8]);
+        assert_eq!(ppu.buffer[16..24], [0x001f; 8]);
+        assert_eq!(ppu.buffer[24..32], [0x7c00; 8]);
+    }
+}
@@ -0,0 +1,104 @@
+use util::*;
+
+#[derive(Clone, Copy)]
+pub struct Mosaic {
+    pub mosaic: u16, // Raw mosaic size register, write only
+
+    // Vertical counters run across scanlines, a new line is sampled
+    // every time they reach the block height
+    pub bg_count: u16,
+    pub bg_line: u16, // Line sampled by mosaic backgrounds
+    pub obj_count: u16,
+    pub obj_line: u16, // Line sampled by mosaic sprites
+}
+
+snapshot!(Mosaic {
+    mosaic,
+    bg_count,
+    bg_line,
+    obj_count,
+    obj_line
+});
+
+impl Mosaic {
+    pub fn new() -> Self {
+        Self {
+            mosaic: 0,
+            bg_count: 0,
+            bg_line: 0,
+            obj_count: 0,
+            obj_line: 0,
+        }
+    }
+
+    /// Advance the vertical counters to line `vcount`
+    pub fn next_line(&mut self, vcount: u16) {
+        if vcount == 0 {
+            *self = Self {
+                mosaic: self.mosaic,
+                ..Self::new()
+            };
+            return;
+        }
+
+        self.bg_count += 1;
+        if self.bg_count >= self.bg_height() {
+            self.bg_count = 0;
+            self.bg_line = vcount;
+        }
+
+        self.obj_count += 1;
+        if self.obj_count >= self.obj_height() {
+            self.obj_count = 0;
+            self.obj_line = vcount;
+        }
+    }
+
+    #[inline]
+    pub fn bg_width(&self) -> u16 {
+        self.mosaic.bits(3, 0) as u16 + 1
+    }
+
+    #[inline]
+    pub fn bg_height(&self) -> u16 {
+        self.mosaic.bits(7, 4) as u16 + 1
+    }
+
+    #[inline]
+    pub fn obj_width(&self) -> u16 {
+        self.mosaic.bits(11, 8) as u16 + 1
+    }
+
+    #[inline]
+    pub fn obj_height(&self) -> u16 {
+        self.mosaic.bits(15, 12) as u16 + 1
+    }
+
+    pub fn set_mosaic(&mut self, value: u16) {
+        self.mosaic = value;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vertical_counter() {
+        let mut mosaic = Mosaic::new();
+        mosaic.set_mosaic(0x0020);
+
+        let lines: Vec<u16> = (0..8)
+            .map(|v| {
+                mosaic.next_line(v);
+                mosaic.bg_line
+            })
+            .collect();
+        assert_eq!(lines, [0, 0, 0, 3, 3, 3, 6, 6]);
+
+        // A new size takes effect from the current count
+        mosaic.set_mosaic(0);
+        mosaic.next_line(8);
+        assert_eq!(mosaic.bg_line, 8);
+    }
+}
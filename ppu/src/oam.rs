@@ -1,12 +1,14 @@
 use crate::Sprite;
 
-use util::Bus;
+use util::{snapshot, Bus};
 
 pub struct Oam {
     pub sprite: [Sprite; 128],
     pub param: [u16; 256],
 }
 
+snapshot!(Oam { sprite, param });
+
 impl Oam {
     pub fn new() -> Self {
         Self {
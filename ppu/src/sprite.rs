@@ -1,3 +1,4 @@
+use crate::layer::OBJ;
 use crate::Ppu;
 use util::*;
 
@@ -32,6 +33,25 @@ pub struct Sprite {
     pub palette_n: u32, // Palette number (for 16 color sprites)
 }
 
+snapshot!(Sprite {
+    attr,
+    xcoord,
+    ycoord,
+    shape,
+    size,
+    mode,
+    affine_f,
+    double_f,
+    mosaic_f,
+    palette_f,
+    hflip,
+    vflip,
+    affine_i,
+    tile_n,
+    priority,
+    palette_n
+});
+
 impl Sprite {
     pub fn new() -> Self {
         Self {
@@ -100,7 +120,19 @@ impl Sprite {
             h *= 2;
         }
 
-        x < 240 && x + w >= 0 && y <= v as i32 && y + h > v as i32
+        x < 240 && x + w >= 0 && y <= v && y + h > v
+    }
+
+    /// Rendering cycles taken on every line the sprite is on
+    #[inline]
+    pub fn cycles(&self) -> u32 {
+        let (width, _) = self.get_dimension();
+
+        match (self.affine_f, self.double_f) {
+            (false, _) => width,
+            (true, false) => 10 + width * 2,
+            (true, true) => 10 + width * 4,
+        }
     }
 }
 
@@ -153,6 +185,38 @@ impl Ppu {
         }
     }
 
+    /// Number of sprites, in OAM order, that can be drawn on the current
+    /// line. Those after the one that runs out of cycles are dropped.
+    pub fn sprite_limit(&self) -> usize {
+        // Less time is left if OAM can be accessed during HBlank
+        let mut budget = if self.dispcnt.bit(5) { 954u32 } else { 1210 };
+        let vcount = self.vcount as u32;
+
+        for (i, sprite) in self.oam.sprite.iter().enumerate() {
+            if sprite.disabled() || !sprite.visible(vcount) {
+                continue;
+            }
+
+            match budget.checked_sub(sprite.cycles()) {
+                Some(left) => budget = left,
+                None => return i,
+            }
+        }
+
+        self.oam.sprite.len()
+    }
+
+    /// Lines back to the one sampled by `sprite` and width of mosaic blocks
+    #[inline]
+    pub fn obj_mosaic(&self, sprite: &Sprite) -> (u32, u32) {
+        if sprite.mosaic_f {
+            let m = &self.mosaic;
+            ((self.vcount - m.obj_line) as u32, m.obj_width() as u32)
+        } else {
+            (0, 1)
+        }
+    }
+
     pub fn decode_sprite(&mut self, index: usize) -> Vec<u16> {
         let sprite = &self.oam.sprite[index];
         let (width, height) = sprite.get_dimension();
@@ -189,9 +253,10 @@ impl Ppu {
         let sequential = self.sequential;
         let window = &self.window;
         let (width, height) = sprite.get_dimension();
+        let (dy, size) = self.obj_mosaic(sprite);
 
-        // Vertical wrap around
-        let y = vcount.wrapping_sub(sprite.ycoord) % 256;
+        // Vertical wrap around, mosaic stays within the sprite
+        let y = (vcount.wrapping_sub(sprite.ycoord) % 256).saturating_sub(dy);
         let w = if sequential { width / 8 } else { 32 };
 
         let mut tile_y = y / 8;
@@ -202,8 +267,12 @@ impl Ppu {
         }
 
         for i in 0..width {
-            let mut tile_x = i / 8;
-            let mut pixel_x = i % 8;
+            // Horizontal wrap around
+            let x = (sprite.xcoord + i) % 512;
+
+            let j = i.saturating_sub(x % size);
+            let mut tile_x = j / 8;
+            let mut pixel_x = j % 8;
             if sprite.hflip {
                 tile_x = width / 8 - tile_x - 1;
                 pixel_x = 7 - pixel_x;
@@ -220,12 +289,14 @@ impl Ppu {
 
             let palette_entry = self.tile_data(sprite.palette_f, tile_b, tile_n, pixel_x, pixel_y);
 
-            // Horizontal wrap around
-            let x = (sprite.xcoord + i) % 512;
             let color = self.obj_palette(sprite.palette_f, sprite.palette_n, palette_entry);
 
-            let layer = &mut self.layer[sprite.priority as usize];
-            layer.paint(x, color, window, 4);
+            if sprite.mode == 2 {
+                self.objwin.mask(x, color);
+            } else {
+                let layer = &mut self.layer[OBJ as usize];
+                layer.paint_sprite(x, color, window, sprite.priority, sprite.mode == 1);
+            }
         }
     }
 
@@ -255,12 +326,17 @@ impl Ppu {
         xcenter %= 512;
         ycenter %= 256;
 
-        let y = vcount as i32 - ycenter;
+        let (dy, size) = self.obj_mosaic(sprite);
+        let top = ycenter - half_height;
+        let y = (vcount as i32 - dy as i32).max(top) - ycenter;
         let w = if sequential { width / 8 } else { 32 };
 
         let (pa, pb, pc, pd) = sprite.get_affine_matrix(&mut self.oam.param);
 
-        for x in -half_width..half_width {
+        for i in -half_width..half_width {
+            let screen_x = (xcenter + i) as u32;
+            let x = (i - (screen_x % 512 % size) as i32).max(-half_width);
+
             // Due to the linearity of the transform matrix, the origin is preserved.
             // That is, the screen origin overlaps the texture origin.
             // The transform matrix takes relative ONSCREEN distance to the origin as input
@@ -288,11 +364,14 @@ impl Ppu {
 
             let palette_entry = self.tile_data(sprite.palette_f, tile_b, tile_n, pixel_x, pixel_y);
 
-            let i = (xcenter + x) as u32;
             let color = self.obj_palette(sprite.palette_f, sprite.palette_n, palette_entry);
 
-            let layer = &mut self.layer[sprite.priority as usize];
-            layer.paint(i, color, window, 4);
+            if sprite.mode == 2 {
+                self.objwin.mask(screen_x, color);
+            } else {
+                let layer = &mut self.layer[OBJ as usize];
+                layer.paint_sprite(screen_x, color, window, sprite.priority, sprite.mode == 1);
+            }
         }
     }
 }
@@ -12,6 +12,14 @@ pub struct Window {
     pub cnt: [u8; 256],
 }
 
+// Control of each pixel is redrawn every line
+snapshot!(Window {
+    winh,
+    winv,
+    winin,
+    winout
+});
+
 impl Window {
     pub fn new() -> Self {
         Self {
@@ -62,10 +70,9 @@ impl Window {
         }
     }
 
-    #[allow(dead_code)]
     pub fn draw_objwin(&mut self, layer: &Layer) {
-        for (i, cnt) in self.cnt.iter_mut().enumerate() {
-            if layer.pixel[i] != TRANSPARENT {
+        for (cnt, pixel) in self.cnt.iter_mut().zip(layer.pixel.iter()) {
+            if *pixel != TRANSPARENT {
                 *cnt = (self.winout >> 8) as u8;
             }
         }
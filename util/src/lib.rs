@@ -2,8 +2,10 @@
 use std::convert::TryInto;
 
 mod bitwise;
+pub mod snapshot;
 
 pub use log::*;
+pub use snapshot::Snapshot;
 
 pub trait BitField {
     fn bit(self, b: u32) -> bool;
@@ -18,7 +20,7 @@ impl<T: std::convert::Into<u32>> BitField for T {
     /// ```
     #[inline]
     fn bits(self, hi: u32, lo: u32) -> u32 {
-        ((self.into() >> lo) & ((1 << (hi - lo + 1)) - 1)) as u32
+        (self.into() >> lo) & ((1 << (hi - lo + 1)) - 1)
     }
 
     /// Test certains bit of a integer, return true if set
@@ -61,6 +63,28 @@ pub fn sign_extend(a: u32, s: u32) -> i32 {
     }
 }
 
+/// Bus cycle type of a memory access
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    NonSeq, // Address unrelated to the previous access
+    Seq,    // Address follows the previous access
+}
+
+impl Snapshot for Access {
+    fn save_state(&self, out: &mut Vec<u8>) {
+        (*self as u8).save_state(out);
+    }
+
+    fn load_state(&mut self, input: &mut &[u8]) -> std::io::Result<()> {
+        *self = match snapshot::take(input, 1)?[0] {
+            0 => Access::NonSeq,
+            1 => Access::Seq,
+            _ => return Err(snapshot::invalid("access type")),
+        };
+        Ok(())
+    }
+}
+
 pub trait Bus {
     #[allow(unused_variables)]
     fn load8(&self, address: usize) -> u8 {
@@ -95,6 +119,21 @@ pub trait Bus {
         self.store16(address, lo);
         self.store16(address + 2, hi);
     }
+    /// Cycles taken by an access, `size` is 0 - byte, 1 - halfword, 2 - word
+    #[allow(unused_variables)]
+    #[inline]
+    fn access_cycles(&mut self, address: usize, size: u32, access: Access) -> i32 {
+        1
+    }
+    /// Cycles taken by an opcode fetch
+    #[inline]
+    fn fetch_cycles(&mut self, address: usize, size: u32, access: Access) -> i32 {
+        self.access_cycles(address, size, access)
+    }
+    /// Internal cycles during which the CPU leaves the bus idle
+    #[allow(unused_variables)]
+    #[inline]
+    fn idle(&mut self, cycles: i32) {}
     /// Print invalid memory access
     fn unhandled<T: Default>(load: bool, size: u32, address: usize) -> T {
         let s = if load { "load" } else { "store" };
@@ -145,6 +184,34 @@ pub fn into32(a: &[u8]) -> u32 {
     u32::from_le_bytes(a[0..4].try_into().unwrap())
 }
 
+/// CRC32 lookup table, reflected polynomial 0xedb88320
+static CRC_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                crc >> 1 ^ 0xedb88320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// CRC32 as used by zlib and PNG
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, &b| {
+        CRC_TABLE[(crc as u8 ^ b) as usize] ^ crc >> 8
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -159,6 +226,11 @@ mod tests {
         assert!(0x80000000u32.bit(31));
     }
 
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+    }
+
     #[test]
     fn test_sign_extend() {
         assert_eq!(sign_extend(0b10, 1), -2);
@@ -0,0 +1,234 @@
+//! Compact binary serialization of emulator state, used by save states.
+//! Values are written little endian in field order, without any tags.
+
+use std::cell::Cell;
+use std::convert::TryInto;
+use std::io::{Error, ErrorKind, Result};
+
+pub trait Snapshot {
+    fn save_state(&self, out: &mut Vec<u8>);
+    fn load_state(&mut self, input: &mut &[u8]) -> Result<()>;
+
+    /// Slices of bytes are copied in one go
+    fn save_slice(items: &[Self], out: &mut Vec<u8>)
+    where
+        Self: Sized,
+    {
+        items.iter().for_each(|i| i.save_state(out));
+    }
+
+    fn load_slice(items: &mut [Self], input: &mut &[u8]) -> Result<()>
+    where
+        Self: Sized,
+    {
+        items.iter_mut().try_for_each(|i| i.load_state(input))
+    }
+}
+
+/// Implement `Snapshot` for a struct by listing the fields to be saved,
+/// fields left out keep their current value on load
+#[macro_export]
+macro_rules! snapshot {
+    ($t:ty { $($f:ident),* $(,)? }) => {
+        impl $crate::Snapshot for $t {
+            fn save_state(&self, out: &mut Vec<u8>) {
+                $($crate::Snapshot::save_state(&self.$f, out);)*
+            }
+
+            fn load_state(&mut self, input: &mut &[u8]) -> std::io::Result<()> {
+                $($crate::Snapshot::load_state(&mut self.$f, input)?;)*
+                Ok(())
+            }
+        }
+    };
+}
+
+/// Take `n` bytes off the front of `input`
+pub fn take<'a>(input: &mut &'a [u8], n: usize) -> Result<&'a [u8]> {
+    if input.len() < n {
+        return Err(Error::new(ErrorKind::UnexpectedEof, "Truncated state"));
+    }
+
+    let (head, tail) = input.split_at(n);
+    *input = tail;
+    Ok(head)
+}
+
+pub fn invalid(what: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("Invalid {} in state", what))
+}
+
+macro_rules! primitive {
+    ($($t:ty),*) => {$(
+        impl Snapshot for $t {
+            fn save_state(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_le_bytes());
+            }
+
+            fn load_state(&mut self, input: &mut &[u8]) -> Result<()> {
+                let bytes = take(input, std::mem::size_of::<$t>())?;
+                *self = <$t>::from_le_bytes(bytes.try_into().unwrap());
+                Ok(())
+            }
+        }
+    )*};
+}
+
+primitive!(u16, u32, u64, i8, i16, i32, i64);
+
+impl Snapshot for u8 {
+    fn save_state(&self, out: &mut Vec<u8>) {
+        out.push(*self);
+    }
+
+    fn load_state(&mut self, input: &mut &[u8]) -> Result<()> {
+        *self = take(input, 1)?[0];
+        Ok(())
+    }
+
+    fn save_slice(items: &[Self], out: &mut Vec<u8>) {
+        out.extend_from_slice(items);
+    }
+
+    fn load_slice(items: &mut [Self], input: &mut &[u8]) -> Result<()> {
+        items.copy_from_slice(take(input, items.len())?);
+        Ok(())
+    }
+}
+
+/// Saved as 64 bits regardless of host
+impl Snapshot for usize {
+    fn save_state(&self, out: &mut Vec<u8>) {
+        (*self as u64).save_state(out);
+    }
+
+    fn load_state(&mut self, input: &mut &[u8]) -> Result<()> {
+        let mut value = 0u64;
+        value.load_state(input)?;
+        *self = value as usize;
+        Ok(())
+    }
+}
+
+impl Snapshot for bool {
+    fn save_state(&self, out: &mut Vec<u8>) {
+        out.push(*self as u8);
+    }
+
+    fn load_state(&mut self, input: &mut &[u8]) -> Result<()> {
+        *self = match take(input, 1)?[0] {
+            0 => false,
+            1 => true,
+            _ => return Err(invalid("bool")),
+        };
+        Ok(())
+    }
+}
+
+impl<T: Snapshot, const N: usize> Snapshot for [T; N] {
+    fn save_state(&self, out: &mut Vec<u8>) {
+        T::save_slice(self, out);
+    }
+
+    fn load_state(&mut self, input: &mut &[u8]) -> Result<()> {
+        T::load_slice(self, input)
+    }
+}
+
+/// Length prefixed, resized on load
+impl<T: Snapshot + Default> Snapshot for Vec<T> {
+    fn save_state(&self, out: &mut Vec<u8>) {
+        (self.len() as u32).save_state(out);
+        T::save_slice(self, out);
+    }
+
+    fn load_state(&mut self, input: &mut &[u8]) -> Result<()> {
+        let mut len = 0u32;
+        len.load_state(input)?;
+        if len as usize > input.len() {
+            return Err(invalid("length"));
+        }
+
+        self.resize_with(len as usize, T::default);
+        T::load_slice(self, input)
+    }
+}
+
+/// Presence is decided by the cartridge, so it must match on load
+impl<T: Snapshot> Snapshot for Option<T> {
+    fn save_state(&self, out: &mut Vec<u8>) {
+        self.is_some().save_state(out);
+        if let Some(value) = self {
+            value.save_state(out);
+        }
+    }
+
+    fn load_state(&mut self, input: &mut &[u8]) -> Result<()> {
+        let mut some = false;
+        some.load_state(input)?;
+        match (self, some) {
+            (Some(value), true) => value.load_state(input),
+            (None, false) => Ok(()),
+            _ => Err(invalid("optional component")),
+        }
+    }
+}
+
+impl<T: Snapshot + Copy> Snapshot for Cell<T> {
+    fn save_state(&self, out: &mut Vec<u8>) {
+        self.get().save_state(out);
+    }
+
+    fn load_state(&mut self, input: &mut &[u8]) -> Result<()> {
+        self.get_mut().load_state(input)
+    }
+}
+
+impl<A: Snapshot, B: Snapshot> Snapshot for (A, B) {
+    fn save_state(&self, out: &mut Vec<u8>) {
+        self.0.save_state(out);
+        self.1.save_state(out);
+    }
+
+    fn load_state(&mut self, input: &mut &[u8]) -> Result<()> {
+        self.0.load_state(input)?;
+        self.1.load_state(input)
+    }
+}
+
+impl<A: Snapshot, B: Snapshot, C: Snapshot, D: Snapshot> Snapshot for (A, B, C, D) {
+    fn save_state(&self, out: &mut Vec<u8>) {
+        self.0.save_state(out);
+        self.1.save_state(out);
+        self.2.save_state(out);
+        self.3.save_state(out);
+    }
+
+    fn load_state(&mut self, input: &mut &[u8]) -> Result<()> {
+        self.0.load_state(input)?;
+        self.1.load_state(input)?;
+        self.2.load_state(input)?;
+        self.3.load_state(input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let value = (vec![1u8, 2, 3], [(true, -5i32); 2]);
+        let mut out = Vec::new();
+        value.save_state(&mut out);
+
+        let mut loaded = (Vec::new(), [(false, 0); 2]);
+        let mut input = out.as_slice();
+        loaded.load_state(&mut input).unwrap();
+        assert_eq!(loaded, value);
+        assert!(input.is_empty());
+
+        let mut input = &out[..out.len() - 1];
+        assert!(loaded.load_state(&mut input).is_err());
+    }
+}